@@ -0,0 +1,385 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{Config, Marker, TestCase};
+
+/// Bumped whenever the cache layout or what gets collected from a file
+/// changes, so caches written by other versions are thrown away.
+const VERSION: u32 = 8;
+
+const CACHE_FILE: &str = "collection.json";
+
+/// How recently a file can have been modified for its modification time to
+/// still be trusted, covering filesystems with coarse timestamps.
+const RACY_NANOS: u128 = 2_000_000_000;
+
+/// The tests collected from each file on previous runs, keyed on the file's
+/// path relative to the rootdir.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CollectionCache {
+    version: u32,
+    /// The collection options in effect when the tests were cached, since
+    /// they decide which functions and classes are tests.
+    options: Vec<Vec<String>>,
+    files: HashMap<String, CachedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    mtime: u128,
+    size: u64,
+    hash: u64,
+    tests: Vec<CachedTest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedTest {
+    name: String,
+    parametrized: bool,
+    markers: Vec<Marker>,
+    doctest: bool,
+}
+
+/// Identifies a version of a file; the hash is only compared when the
+/// modification time or size changed.
+#[derive(Debug, Clone, Copy)]
+pub struct Fingerprint {
+    mtime: u128,
+    size: u64,
+    hash: Option<u64>,
+}
+
+impl Fingerprint {
+    pub fn new(metadata: &Metadata) -> Fingerprint {
+        Fingerprint {
+            mtime: metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_nanos()),
+            size: metadata.len(),
+            hash: None,
+        }
+    }
+
+    pub fn with_source(self, source: &str) -> Fingerprint {
+        Fingerprint {
+            hash: Some(xxh3_64(source.as_bytes())),
+            ..self
+        }
+    }
+}
+
+/// The tests collected from a file, to be recorded in the cache once
+/// collection is done.
+pub struct CacheUpdate {
+    file: String,
+    /// Whether the file was read, as opposed to found unchanged by its
+    /// modification time and size.
+    collected: bool,
+    entry: Option<CachedFile>,
+}
+
+impl CacheUpdate {
+    /// Files that failed to collect aren't cached, so the error is reported
+    /// again next time. Neither are files that were `imported` to find their
+    /// tests, as those depend on more than the file's own source.
+    pub fn new(
+        file: &str,
+        fingerprint: &Fingerprint,
+        tests: &[TestCase],
+        imported: bool,
+    ) -> CacheUpdate {
+        let entry = match fingerprint.hash {
+            Some(hash) if !imported && tests.iter().all(|test| test.error.is_none()) => {
+                Some(CachedFile {
+                    // a file modified just now could change again without its
+                    // modification time moving, so only trust the hash for it
+                    mtime: if now().saturating_sub(fingerprint.mtime) < RACY_NANOS {
+                        0
+                    } else {
+                        fingerprint.mtime
+                    },
+                    size: fingerprint.size,
+                    hash,
+                    tests: tests
+                        .iter()
+                        .map(|test| CachedTest {
+                            name: test.name.clone(),
+                            parametrized: test.parametrized,
+                            markers: test.markers.clone(),
+                            doctest: test.doctest,
+                        })
+                        .collect(),
+                })
+            }
+            _ => None,
+        };
+
+        CacheUpdate {
+            file: file.to_string(),
+            collected: fingerprint.hash.is_some(),
+            entry,
+        }
+    }
+}
+
+impl CollectionCache {
+    /// Loads the cache for `config`, starting afresh if it's missing,
+    /// unreadable, from another version or for other collection options.
+    pub fn load(config: &Config) -> CollectionCache {
+        let empty = CollectionCache {
+            version: VERSION,
+            options: options(config),
+            files: HashMap::new(),
+        };
+
+        let Some(cache_dir) = &config.cache_dir else {
+            return empty;
+        };
+
+        match fs::read_to_string(cache_dir.join(CACHE_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CollectionCache>(&contents).ok())
+        {
+            Some(cache) if cache.version == empty.version && cache.options == empty.options => {
+                cache
+            }
+            _ => empty,
+        }
+    }
+
+    /// Returns the cached tests for a file if it's unchanged, comparing the
+    /// content hash when the fingerprint has one.
+    pub fn get(&self, file: &str, fingerprint: &Fingerprint) -> Option<Vec<TestCase>> {
+        let cached = self.files.get(file)?;
+        let unchanged = match fingerprint.hash {
+            Some(hash) => cached.hash == hash,
+            None => cached.mtime == fingerprint.mtime && cached.size == fingerprint.size,
+        };
+        if !unchanged {
+            return None;
+        }
+
+        Some(
+            cached
+                .tests
+                .iter()
+                .map(|test| TestCase {
+                    parametrized: test.parametrized,
+                    markers: test.markers.clone(),
+                    doctest: test.doctest,
+                    ..TestCase::new(file, &test.name)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn update(&mut self, update: CacheUpdate) {
+        match update.entry {
+            Some(entry) => {
+                self.files.insert(update.file, entry);
+            }
+            None if update.collected => {
+                self.files.remove(&update.file);
+            }
+            None => {}
+        }
+    }
+
+    /// Writes the cache, dropping files that no longer exist.
+    pub fn save(mut self, config: &Config) -> Result<()> {
+        let Some(cache_dir) = &config.cache_dir else {
+            return Ok(());
+        };
+
+        self.files
+            .retain(|file, _| config.rootdir.join(file).is_file());
+
+        create_cache_dir(cache_dir)?;
+        // write then rename, so concurrent runs never see a partial file
+        let tmp = cache_dir.join(format!("{}.{}", CACHE_FILE, std::process::id()));
+        fs::write(&tmp, serde_json::to_string(&self)?)?;
+        fs::rename(&tmp, cache_dir.join(CACHE_FILE))?;
+
+        Ok(())
+    }
+}
+
+/// Removes the cache directory, as `--cache-clear` asks.
+pub fn clear(config: &Config) -> Result<()> {
+    match &config.cache_dir {
+        Some(cache_dir) if cache_dir.is_dir() => Ok(fs::remove_dir_all(cache_dir)?),
+        _ => Ok(()),
+    }
+}
+
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos())
+}
+
+fn options(config: &Config) -> Vec<Vec<String>> {
+    vec![
+        // decides which modules are only searched for doctests
+        config.python_files.patterns().to_vec(),
+        config.python_classes.patterns().to_vec(),
+        config.python_functions.patterns().to_vec(),
+        vec![
+            config.dynamic_collection.to_string(),
+            config.doctest_modules.to_string(),
+        ],
+        config.doctest_globs.clone(),
+        config
+            .pythonpath
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect(),
+    ]
+}
+
+fn create_cache_dir(cache_dir: &Path) -> Result<()> {
+    if cache_dir.is_dir() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(cache_dir)?;
+    fs::write(
+        cache_dir.join(".gitignore"),
+        "# Created by rytest automatically.\n*\n",
+    )?;
+    fs::write(
+        cache_dir.join("README.md"),
+        "# rytest cache directory\n\nThis directory contains data from rytest's collection cache.\n\n\
+         **Do not** commit this to version control. Run `rytest --cache-clear` to remove it.\n",
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Patterns;
+
+    fn fingerprint(mtime: u128, source: &str) -> Fingerprint {
+        Fingerprint {
+            mtime,
+            size: source.len() as u64,
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn it_returns_unchanged_files() {
+        let mut cache = CollectionCache::default();
+        let source = "def test_one(): pass";
+        cache.update(CacheUpdate::new(
+            "test_file.py",
+            &fingerprint(1, source).with_source(source),
+            &[TestCase::new("test_file.py", "test_one")],
+            false,
+        ));
+
+        let tests = cache.get("test_file.py", &fingerprint(1, source)).unwrap();
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].name, "test_one");
+    }
+
+    #[test]
+    fn it_compares_hashes_when_modified() {
+        let mut cache = CollectionCache::default();
+        let source = "def test_one(): pass";
+        cache.update(CacheUpdate::new(
+            "test_file.py",
+            &fingerprint(1, source).with_source(source),
+            &[TestCase::new("test_file.py", "test_one")],
+            false,
+        ));
+
+        // touched but unchanged
+        assert!(cache.get("test_file.py", &fingerprint(2, source)).is_none());
+        assert!(cache
+            .get("test_file.py", &fingerprint(2, source).with_source(source))
+            .is_some());
+
+        let changed = "def test_two(): pass";
+        assert!(cache
+            .get(
+                "test_file.py",
+                &fingerprint(2, changed).with_source(changed)
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn it_skips_files_with_errors() {
+        let mut cache = CollectionCache::default();
+        let source = "def test_one(";
+        let mut test = TestCase::new("test_file.py", "");
+        test.error = Some(pyo3::PyErr::new::<pyo3::exceptions::PySyntaxError, _>(
+            "Error parsing",
+        ));
+        cache.update(CacheUpdate::new(
+            "test_file.py",
+            &fingerprint(1, source).with_source(source),
+            &[test],
+            false,
+        ));
+
+        assert!(cache.get("test_file.py", &fingerprint(1, source)).is_none());
+    }
+
+    #[test]
+    fn it_is_thrown_away_when_python_files_changes() {
+        let root = tempfile::tempdir().unwrap();
+        let source = "def test_one(): pass";
+        fs::write(root.path().join("test_file.py"), source).unwrap();
+        let config = Config {
+            cache_dir: Some(root.path().join(".rytest_cache")),
+            python_files: Patterns::files(&["test_*.py".to_string()]).unwrap(),
+            rootdir: root.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let mut cache = CollectionCache::load(&config);
+        cache.update(CacheUpdate::new(
+            "test_file.py",
+            &fingerprint(1, source).with_source(source),
+            &[TestCase::new("test_file.py", "test_one")],
+            false,
+        ));
+        cache.save(&config).unwrap();
+        assert!(CollectionCache::load(&config)
+            .get("test_file.py", &fingerprint(1, source))
+            .is_some());
+
+        let changed = Config {
+            python_files: Patterns::files(&["check_*.py".to_string()]).unwrap(),
+            ..config
+        };
+        assert!(CollectionCache::load(&changed)
+            .get("test_file.py", &fingerprint(1, source))
+            .is_none());
+    }
+
+    #[test]
+    fn it_skips_imported_files() {
+        let mut cache = CollectionCache::default();
+        let source = "def test_one(): pass";
+        cache.update(CacheUpdate::new(
+            "test_file.py",
+            &fingerprint(1, source).with_source(source),
+            &[TestCase::new("test_file.py", "test_one")],
+            true,
+        ));
+
+        assert!(cache.get("test_file.py", &fingerprint(1, source)).is_none());
+    }
+}
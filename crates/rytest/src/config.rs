@@ -0,0 +1,415 @@
+use anyhow::{anyhow, Result};
+use configparser::ini::Ini;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Settings read from a configuration file, normalized to lists of strings so
+/// TOML arrays and whitespace separated INI values look the same.
+#[derive(Debug, Default)]
+pub struct FileConfig {
+    pub path: PathBuf,
+    values: HashMap<String, Vec<String>>,
+}
+
+impl FileConfig {
+    pub fn get(&self, key: &str) -> Option<&Vec<String>> {
+        self.values.get(key)
+    }
+
+    /// The directory containing the configuration file, which relative paths
+    /// within it are resolved against.
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+}
+
+/// Keys holding a list of lines, one entry per line in INI files.
+const LINELIST_KEYS: [&str; 1] = ["markers"];
+
+/// Keys holding command line arguments, split like a shell would in INI files.
+const ARGS_KEYS: [&str; 1] = ["addopts"];
+
+/// Determines the rootdir and configuration file the way pytest does: the
+/// first configuration file found in the common ancestor of `args` or its
+/// parents, otherwise the first directory containing a `setup.py`, otherwise
+/// the common ancestor of `args` and the invocation directory. `--rootdir`
+/// overrides the rootdir but not the configuration file.
+pub fn determine_rootdir(
+    invocation_dir: &Path,
+    args: &[String],
+    rootdir_arg: Option<&str>,
+) -> Result<(PathBuf, Option<FileConfig>)> {
+    let dirs: Vec<PathBuf> = args
+        .iter()
+        .map(|arg| normalize_path(&invocation_dir.join(arg.split("::").next().unwrap_or(arg))))
+        .filter(|path| path.exists())
+        .map(|path| match path.is_file() {
+            true => path.parent().unwrap_or(&path).to_path_buf(),
+            false => path,
+        })
+        .collect();
+    let ancestor = common_ancestor(&dirs).unwrap_or_else(|| invocation_dir.to_path_buf());
+
+    let mut file_config = find(&ancestor)?;
+    let mut rootdir = file_config.as_ref().map(|c| c.dir().to_path_buf());
+
+    if rootdir.is_none() {
+        rootdir = ancestor
+            .ancestors()
+            .find(|dir| dir.join("setup.py").is_file())
+            .map(|dir| dir.to_path_buf());
+    }
+
+    if rootdir.is_none() && dirs.len() > 1 {
+        for dir in &dirs {
+            if let Some(config) = find(dir)? {
+                rootdir = Some(config.dir().to_path_buf());
+                file_config = Some(config);
+                break;
+            }
+        }
+    }
+
+    let rootdir = rootdir.unwrap_or_else(|| {
+        match common_ancestor(&[invocation_dir.to_path_buf(), ancestor.clone()]) {
+            Some(dir) if dir.parent().is_some() => dir,
+            _ => ancestor,
+        }
+    });
+
+    let rootdir = match rootdir_arg {
+        Some(arg) => normalize_path(&invocation_dir.join(arg)),
+        None => rootdir,
+    };
+
+    Ok((rootdir, file_config))
+}
+
+/// Resolves `.` and `..` in a path without touching the filesystem.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn common_ancestor(paths: &[PathBuf]) -> Option<PathBuf> {
+    let (first, rest) = paths.split_first()?;
+    let mut ancestor = first.clone();
+    for path in rest {
+        while !path.starts_with(&ancestor) {
+            if !ancestor.pop() {
+                return None;
+            }
+        }
+    }
+    Some(ancestor)
+}
+
+/// Finds the first configuration file in `dir` or its parents, preferring
+/// `[tool.rytest]` in pyproject.toml and falling back to the same files and
+/// sections pytest reads.
+pub fn find(dir: &Path) -> Result<Option<FileConfig>> {
+    for dir in dir.ancestors() {
+        if let Some(config) = load_dir(dir)? {
+            return Ok(Some(config));
+        }
+    }
+    Ok(None)
+}
+
+fn load_dir(dir: &Path) -> Result<Option<FileConfig>> {
+    let pyproject = dir.join("pyproject.toml");
+    if pyproject.is_file() {
+        if let Some(values) = parse_toml(&fs::read_to_string(&pyproject)?, &["tool", "rytest"])
+            .map_err(|e| anyhow!("{}: {}", pyproject.display(), e))?
+        {
+            return Ok(Some(FileConfig {
+                path: pyproject,
+                values,
+            }));
+        }
+    }
+
+    // the same files, sections and order pytest searches in
+    let candidates: [(&str, &str); 5] = [
+        ("pytest.ini", "pytest"),
+        (".pytest.ini", "pytest"),
+        ("pyproject.toml", "tool.pytest.ini_options"),
+        ("tox.ini", "pytest"),
+        ("setup.cfg", "tool:pytest"),
+    ];
+
+    for (file, section) in candidates {
+        let path = dir.join(file);
+        if !path.is_file() {
+            continue;
+        }
+
+        let contents = fs::read_to_string(&path)?;
+        let values = if file == "pyproject.toml" {
+            parse_toml(&contents, &["tool", "pytest", "ini_options"])
+        } else {
+            parse_ini(&contents, section)
+        }
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+
+        match values {
+            Some(values) => return Ok(Some(FileConfig { path, values })),
+            // pytest.ini is used even if it has no [pytest] section
+            None if file == "pytest.ini" || file == ".pytest.ini" => {
+                return Ok(Some(FileConfig {
+                    path,
+                    values: HashMap::new(),
+                }))
+            }
+            None => {}
+        }
+    }
+
+    Ok(None)
+}
+
+/// Expands the glob patterns in `testpaths`, relative to `dir`, the way pytest
+/// does, leaving out patterns that match nothing.
+pub fn expand_testpaths(dir: &Path, testpaths: &[String]) -> Vec<PathBuf> {
+    let dir = glob::Pattern::escape(&dir.to_string_lossy());
+    // as in Python, wildcards don't match hidden files
+    let options = glob::MatchOptions {
+        require_literal_leading_dot: true,
+        ..Default::default()
+    };
+    testpaths
+        .iter()
+        .filter_map(|testpath| glob::glob_with(&format!("{}/{}", dir, testpath), options).ok())
+        .flat_map(|paths| {
+            let mut paths: Vec<PathBuf> = paths.filter_map(Result::ok).collect();
+            paths.sort();
+            paths
+        })
+        .map(|path| normalize_path(&path))
+        .collect()
+}
+
+fn parse_toml(contents: &str, table: &[&str]) -> Result<Option<HashMap<String, Vec<String>>>> {
+    let mut value = toml::Value::Table(contents.parse::<toml::Table>()?);
+    for key in table {
+        value = match value.get(key) {
+            Some(value) => value.clone(),
+            None => return Ok(None),
+        };
+    }
+
+    let table = value
+        .as_table()
+        .ok_or_else(|| anyhow!("[{}] is not a table", table.join(".")))?;
+
+    let mut values = HashMap::new();
+    for (key, value) in table {
+        let list = match value {
+            toml::Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    toml::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect(),
+            toml::Value::String(s) => split_value(key, s)?,
+            other => vec![other.to_string()],
+        };
+        values.insert(key.clone(), list);
+    }
+
+    Ok(Some(values))
+}
+
+fn parse_ini(contents: &str, section: &str) -> Result<Option<HashMap<String, Vec<String>>>> {
+    let mut ini = Ini::new_cs();
+    ini.set_multiline(true);
+    let sections = ini.read(contents.to_string()).map_err(|e| anyhow!(e))?;
+
+    let Some(options) = sections.get(section) else {
+        return Ok(None);
+    };
+
+    let mut values = HashMap::new();
+    for (key, value) in options {
+        let value = value.clone().unwrap_or_default();
+        values.insert(key.clone(), split_value(key, &value)?);
+    }
+
+    Ok(Some(values))
+}
+
+/// Splits a string value the way pytest does for the type of `key`.
+fn split_value(key: &str, value: &str) -> Result<Vec<String>> {
+    if LINELIST_KEYS.contains(&key) {
+        Ok(value
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect())
+    } else if ARGS_KEYS.contains(&key) {
+        shlex::split(value).ok_or_else(|| anyhow!("could not parse {} = {:?}", key, value))
+    } else {
+        Ok(value.split_whitespace().map(|s| s.to_string()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::indoc::indoc;
+    use std::env;
+
+    #[test]
+    fn it_normalizes_paths() {
+        assert_eq!(
+            normalize_path(Path::new("/a/./b/../c")),
+            PathBuf::from("/a/c")
+        );
+    }
+
+    #[test]
+    fn it_finds_common_ancestors() {
+        let paths = vec![PathBuf::from("/a/b/c"), PathBuf::from("/a/b/d/e")];
+        assert_eq!(common_ancestor(&paths), Some(PathBuf::from("/a/b")));
+        assert_eq!(common_ancestor(&[]), None);
+    }
+
+    #[test]
+    fn it_uses_the_configuration_file_directory_as_rootdir() {
+        let invocation_dir = env::current_dir()
+            .unwrap()
+            .join("tests/input/config/checks");
+        let (rootdir, file_config) = determine_rootdir(&invocation_dir, &[], None).unwrap();
+        assert_eq!(
+            rootdir,
+            env::current_dir().unwrap().join("tests/input/config")
+        );
+        assert_eq!(
+            file_config.unwrap().path,
+            env::current_dir()
+                .unwrap()
+                .join("tests/input/config/pyproject.toml")
+        );
+    }
+
+    #[test]
+    fn it_falls_back_to_the_invocation_directory() {
+        let invocation_dir = env::current_dir().unwrap();
+        let args = vec![
+            "tests/input/good".to_string(),
+            "tests/input/bad".to_string(),
+        ];
+        let (rootdir, _) = determine_rootdir(&invocation_dir, &args, None).unwrap();
+        assert_eq!(rootdir, invocation_dir);
+    }
+
+    #[test]
+    fn it_prefers_the_rootdir_argument() {
+        let invocation_dir = env::current_dir().unwrap();
+        let (rootdir, _) =
+            determine_rootdir(&invocation_dir, &[], Some("tests/./input/../input")).unwrap();
+        assert_eq!(rootdir, invocation_dir.join("tests/input"));
+    }
+
+    #[test]
+    fn it_parses_toml_tables() {
+        let contents = indoc! {r#"
+            [tool.pytest.ini_options]
+            testpaths = ["tests", "integration"]
+            addopts = "-v --strict-markers -m 'not slow'"
+            python_functions = "test_* check_*"
+        "#};
+        let values = parse_toml(contents, &["tool", "pytest", "ini_options"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(values["testpaths"], vec!["tests", "integration"]);
+        assert_eq!(
+            values["addopts"],
+            vec!["-v", "--strict-markers", "-m", "not slow"]
+        );
+        assert_eq!(values["python_functions"], vec!["test_*", "check_*"]);
+    }
+
+    #[test]
+    fn it_ignores_missing_toml_tables() {
+        let contents = indoc! {r#"
+            [project]
+            name = "example"
+        "#};
+        assert!(parse_toml(contents, &["tool", "rytest"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn it_parses_ini_sections() {
+        let contents = indoc! {"
+            [metadata]
+            name = example
+
+            [tool:pytest]
+            testpaths = tests
+            markers =
+                slow: marks tests as slow
+                db
+            addopts = -x
+        "};
+        let values = parse_ini(contents, "tool:pytest").unwrap().unwrap();
+        assert_eq!(values["testpaths"], vec!["tests"]);
+        assert_eq!(values["markers"], vec!["slow: marks tests as slow", "db"]);
+        assert_eq!(values["addopts"], vec!["-x"]);
+    }
+
+    #[test]
+    fn it_ignores_missing_ini_sections() {
+        let contents = indoc! {"
+            [tox]
+            envlist = py312
+        "};
+        assert!(parse_ini(contents, "pytest").unwrap().is_none());
+    }
+
+    #[test]
+    fn it_uses_an_empty_hidden_pytest_ini() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join(".pytest.ini"), "").unwrap();
+        let file_config = load_dir(root.path()).unwrap().unwrap();
+        assert_eq!(file_config.path, root.path().join(".pytest.ini"));
+    }
+
+    #[test]
+    fn it_expands_testpaths_globs() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in [
+            "tests/b/unit",
+            "tests/a/unit",
+            "tests/c/integration",
+            "docs",
+        ] {
+            fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+        let testpaths = vec![
+            "tests/*/unit".to_string(),
+            "docs".to_string(),
+            "missing".to_string(),
+        ];
+        assert_eq!(
+            expand_testpaths(root.path(), &testpaths),
+            vec![
+                root.path().join("tests/a/unit"),
+                root.path().join("tests/b/unit"),
+                root.path().join("docs"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::{bail, Result};
+
+/// A boolean expression over identifiers, as accepted by pytest's `-m` and
+/// `-k` options, e.g. `slow and not (db or network)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Ident(String),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+    Ident(String),
+}
+
+impl Expression {
+    pub fn parse(input: &str) -> Result<Expression> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expression = parser.expr()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("unexpected {:?} in expression {:?}", token, input);
+        }
+        Ok(expression)
+    }
+
+    /// Evaluates the expression, deciding whether each identifier is true
+    /// with `matcher`.
+    pub fn evaluate(&self, matcher: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            Expression::Ident(ident) => matcher(ident),
+            Expression::Not(expr) => !expr.evaluate(matcher),
+            Expression::And(left, right) => left.evaluate(matcher) && right.evaluate(matcher),
+            Expression::Or(left, right) => left.evaluate(matcher) || right.evaluate(matcher),
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | ':' | '+' | '-' | '.' | '[' | ']' | '\\' | '/')
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::LeftParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::RightParen);
+        } else if is_ident_char(c) {
+            let mut ident = String::new();
+            while let Some(&c) = chars.peek() {
+                if !is_ident_char(c) {
+                    break;
+                }
+                ident.push(c);
+                chars.next();
+            }
+            tokens.push(match ident.as_str() {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                _ => Token::Ident(ident),
+            });
+        } else {
+            bail!("unexpected character {:?} in expression {:?}", c, input);
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn accept(&mut self, token: Token) -> bool {
+        if self.tokens.get(self.pos) == Some(&token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expression> {
+        let mut left = self.and_expr()?;
+        while self.accept(Token::Or) {
+            let right = self.and_expr()?;
+            left = Expression::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expression> {
+        let mut left = self.not_expr()?;
+        while self.accept(Token::And) {
+            let right = self.not_expr()?;
+            left = Expression::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn not_expr(&mut self) -> Result<Expression> {
+        if self.accept(Token::Not) {
+            return Ok(Expression::Not(Box::new(self.not_expr()?)));
+        }
+        if self.accept(Token::LeftParen) {
+            let expr = self.expr()?;
+            if !self.accept(Token::RightParen) {
+                bail!("expected ')' in expression");
+            }
+            return Ok(expr);
+        }
+        match self.tokens.get(self.pos) {
+            Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(Expression::Ident(ident))
+            }
+            Some(token) => bail!("expected identifier, found {:?}", token),
+            None => bail!("expected identifier, found end of expression"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(input: &str, names: &[&str]) -> bool {
+        Expression::parse(input)
+            .unwrap()
+            .evaluate(&|ident| names.contains(&ident))
+    }
+
+    #[test]
+    fn it_evaluates_identifiers() {
+        assert!(evaluate("slow", &["slow"]));
+        assert!(!evaluate("slow", &["db"]));
+    }
+
+    #[test]
+    fn it_evaluates_operators() {
+        assert!(evaluate("slow and not db", &["slow"]));
+        assert!(!evaluate("slow and not db", &["slow", "db"]));
+        assert!(evaluate("db or network", &["network"]));
+    }
+
+    #[test]
+    fn it_binds_and_tighter_than_or() {
+        assert!(evaluate("a or b and c", &["a"]));
+        assert!(!evaluate("(a or b) and c", &["a"]));
+    }
+
+    #[test]
+    fn it_accepts_parametrize_ids() {
+        assert!(evaluate("test_one[1-2]", &["test_one[1-2]"]));
+    }
+
+    #[test]
+    fn it_rejects_invalid_expressions() {
+        assert!(Expression::parse("slow and").is_err());
+        assert!(Expression::parse("(slow").is_err());
+        assert!(Expression::parse("slow db").is_err());
+        assert!(Expression::parse("slow & db").is_err());
+    }
+}
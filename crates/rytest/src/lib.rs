@@ -1,41 +1,157 @@
 use anyhow::Result;
-use clap::{Arg, Command};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command};
 
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::mpsc::{self};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
+mod cache;
+mod config;
+mod expression;
+mod patterns;
 mod phases;
 mod python;
 mod structs;
 
+pub use crate::expression::Expression;
+pub use crate::patterns::Patterns;
 pub use crate::phases::collection;
 pub use crate::phases::execution;
-pub use crate::phases::reporting;
-pub use crate::structs::{Config, TestCase};
+pub use crate::phases::reporting::{self, Reporter};
+pub use crate::structs::{Cancellation, Config, Durations, Event, ExitCode, Marker, TestCase};
 
-pub fn get_args() -> Result<Config> {
-    let matches = Command::new("rytest")
+/// The directories pytest doesn't recurse into unless `norecursedirs` is
+/// configured, plus `__pycache__`.
+const DEFAULT_NORECURSEDIRS: [&str; 10] = [
+    "*.egg",
+    ".*",
+    "_darcs",
+    "build",
+    "CVS",
+    "dist",
+    "node_modules",
+    "venv",
+    "{arch}",
+    "__pycache__",
+];
+
+/// The event loops async tests can run on.
+const ASYNC_BACKENDS: [&str; 3] = ["asyncio", "trio", "uvloop"];
+
+/// The flavours of JUnit XML that pytest can write, `legacy` being `xunit1`.
+const JUNIT_FAMILIES: [&str; 3] = ["legacy", "xunit1", "xunit2"];
+
+/// The outcomes `-r` can list in the short test summary, in pytest's order.
+const REPORT_CHARS: &str = "fEsxXpP";
+
+fn command() -> Command {
+    Command::new("rytest")
         .version("0.1.0")
         .about("rytest is a reasonably fast, somewhat Pytest compatible Python test runner.")
+        // options repeated by addopts and the command line keep the last value
+        .args_override_self(true)
         // An alphabetical list of arguments
+        .arg(
+            Arg::new("async_backend")
+                .long("async-backend")
+                .value_name("NAME")
+                .help("Event loop to run async tests and fixtures on [default: asyncio]")
+                .value_parser(ASYNC_BACKENDS),
+        )
+        .arg(
+            Arg::new("cache_clear")
+                .long("cache-clear")
+                .help("Remove the collection cache before running")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("collect_only")
                 .long("collect-only")
                 .help("only collect tests, don't run them")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("collect_threads")
+                .long("collect-threads")
+                .value_name("N")
+                .help("Number of threads used to parse test files, defaults to the number of CPUs")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Color the output [default: auto]")
+                .value_parser(["yes", "no", "auto"]),
+        )
+        .arg(
+            Arg::new("continue_on_collection_errors")
+                .long("continue-on-collection-errors")
+                .help("Run the tests that could be collected even if others couldn't be")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("deselect")
+                .long("deselect")
+                .value_name("NODEID")
+                .help("Deselect the test(s) with the given node ID prefix. Can be used multiple times")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("doctest_glob")
+                .long("doctest-glob")
+                .value_name("GLOB")
+                .help("Collect doctests from text files matching the glob pattern [default: test*.txt]")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("doctest_modules")
+                .long("doctest-modules")
+                .help("Collect doctests from the docstrings of all python modules")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("durations")
+                .long("durations")
+                .value_name("N")
+                .help("List the N slowest setup, call and teardown durations, or all of them if 0")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("durations_min")
+                .long("durations-min")
+                .value_name("SECONDS")
+                .help("Leave durations shorter than SECONDS out of --durations, unless -vv is given [default: 0.005]")
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("dynamic_collection")
+                .long("dynamic-collection")
+                .help("Also import test modules to find tests created at runtime")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("exitfirst")
+                .short('x')
+                .long("exitfirst")
+                .help("Stop after the first failure, short for --maxfail=1")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("file_prefix")
                 .short('f')
                 .long("file-prefix")
-                .help("The prefix to search for to indicate a file contains tests")
-                .default_value("test_"),
+                .value_name("PREFIX")
+                .help("Only search files starting with this prefix for tests, short for python_files = PREFIX*.py"),
         )
         .arg(
             Arg::new("files")
                 .value_name("FILE")
-                .help("Input file(s)")
+                .help("Input file(s), folder(s) or node ID(s) such as tests/test_file.py::test_name")
                 .default_value(".")
                 .num_args(1..),
         )
@@ -44,7 +160,15 @@ pub fn get_args() -> Result<Config> {
                 .short('i')
                 .long("ignore")
                 .help("Ignore file(s) and folders. Can be used multiple times")
-                .default_value(".venv"),
+                .default_value(".venv")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("ignore_glob")
+                .long("ignore-glob")
+                .value_name("GLOB")
+                .help("Ignore file(s) and folders matching the glob pattern. Can be used multiple times")
+                .action(clap::ArgAction::Append),
         )
         .arg(
             Arg::new("info")
@@ -52,105 +176,573 @@ pub fn get_args() -> Result<Config> {
                 .help("Print information about rytest and the python environment it is running in.")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("junitxml")
+                .long("junitxml")
+                .value_name("PATH")
+                .help("Write a JUnit XML report of the results to PATH")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("keyword")
+                .short('k')
+                .value_name("EXPRESSION")
+                .help("Only run tests whose names, classes, modules or markers match the given expression, e.g. -k 'http and not slow'"),
+        )
+        .arg(
+            Arg::new("markexpr").short('m').value_name("MARKEXPR").help(
+                "Only run tests matching the given marker expression, e.g. -m 'slow and not db'",
+            ),
+        )
+        .arg(
+            Arg::new("maxfail")
+                .long("maxfail")
+                .value_name("N")
+                .help("Stop after N failures")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Less output, with -qq for only failures and the summary")
+                .action(clap::ArgAction::Count),
+        )
+        .arg(
+            Arg::new("report_chars")
+                .short('r')
+                .value_name("CHARS")
+                .help("Show a short summary of the tests with the given outcomes: (f)ailed, (E)rror, (s)kipped, (x)failed, (X)passed, (p)assed, (P)assed with output, (w)arnings (ignored), (a)ll except passed, (A)ll or (N)one [default: fE]"),
+        )
+        .arg(
+            Arg::new("report_json")
+                .long("report-json")
+                .value_name("PATH")
+                .help("Write a JSON report of the results to PATH")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("report_jsonl")
+                .long("report-jsonl")
+                .value_name("PATH")
+                .help("Stream events as JSON Lines to PATH, or to stdout instead of text if -"),
+        )
+        .arg(
+            Arg::new("respect_gitignore")
+                .long("respect-gitignore")
+                .help("Skip files and folders excluded by .gitignore and .ignore files")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("rootdir")
+                .long("rootdir")
+                .value_name("ROOTDIR")
+                .help("Define the root directory for tests, which node IDs are relative to"),
+        )
+        .arg(
+            Arg::new("strict_markers")
+                .long("strict-markers")
+                .help("Error on markers that are not registered in configuration")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("test_prefix")
                 .short('p')
                 .long("test-prefix")
-                .help("The prefix to search for to indicate a function is a test")
-                .default_value("test_"),
+                .value_name("PREFIX")
+                .help("Only collect functions starting with this prefix as tests, short for python_functions = PREFIX*"),
         )
         .arg(
             Arg::new("verbose")
                 .short('v')
                 .long("verbose")
-                .help("Verbose output")
-                .action(clap::ArgAction::SetTrue),
+                .help("A line per test, with -vv for full tracebacks")
+                .action(clap::ArgAction::Count),
         )
-        .get_matches();
+}
 
-    Ok(Config {
-        collect_only: matches.get_flag("collect_only"),
-        file_prefix: matches
-            .get_one::<String>("file_prefix")
+pub fn get_args() -> Result<Config> {
+    let current_dir = env::current_dir()?;
+
+    // the rootdir and configuration file depend on the paths given on the
+    // command line, so find those before the configuration can add arguments
+    let preliminary = get_matches(env::args().collect());
+    let paths: Vec<String> = match preliminary.value_source("files") {
+        Some(ValueSource::CommandLine) => preliminary
+            .get_many::<String>("files")
             .unwrap()
-            .to_string(),
-        files: matches
+            .map(|s| s.to_string())
+            .collect(),
+        _ => vec![],
+    };
+    let (rootdir, file_config) = config::determine_rootdir(
+        &current_dir,
+        &paths,
+        preliminary.get_one::<String>("rootdir").map(|s| s.as_str()),
+    )?;
+
+    // addopts from the configuration file go before the real arguments, so
+    // flags given on the command line take precedence
+    let mut args: Vec<String> = env::args().collect();
+    if let Some(addopts) = file_config.as_ref().and_then(|c| c.get("addopts")) {
+        args.splice(1..1, addopts.iter().cloned());
+    }
+
+    let matches = get_matches(args);
+
+    let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let from_file = |key: &str| {
+        file_config
+            .as_ref()
+            .and_then(|c| c.get(key))
+            .filter(|values| !values.is_empty())
+    };
+
+    let testpaths = match (from_file("testpaths"), &file_config) {
+        (Some(testpaths), Some(file_config)) if !from_cli("files") => {
+            config::expand_testpaths(file_config.dir(), testpaths)
+        }
+        _ => vec![],
+    };
+    // like pytest, the files given fill in when no testpaths exist
+    let args: Vec<String> = match testpaths.is_empty() {
+        false => testpaths
+            .iter()
+            .map(|p| relative_to(p, &current_dir))
+            .collect(),
+        true => matches
             .get_many::<String>("files")
             .unwrap()
             .map(|s| s.to_string())
             .collect(),
+    };
+
+    let mut files: Vec<String> = vec![];
+    let mut node_ids = vec![];
+    for arg in &args {
+        let (path, node_id) = split_node_id(arg, &current_dir, &rootdir);
+        if !current_dir.join(&path).exists() {
+            anyhow::bail!("file or directory not found: {}", arg);
+        }
+        if let Some(node_id) = node_id {
+            node_ids.push(node_id);
+        }
+        if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    // a file or directory given whole is collected whole, even when node IDs
+    // within it are also given
+    let whole: Vec<String> = args
+        .iter()
+        .filter(|arg| !arg.contains("::"))
+        .map(|arg| to_node_id(arg, &current_dir, &rootdir))
+        .collect();
+    node_ids.retain(|node_id: &String| {
+        let file = Path::new(node_id.split("::").next().unwrap_or(node_id));
+        !whole.iter().any(|path| file.starts_with(path))
+    });
+
+    let patterns = |key: &str,
+                    new: fn(&[String]) -> Result<Patterns>,
+                    shorthand: Option<String>,
+                    default: &[&str]| {
+        let patterns: Vec<String> = match (shorthand, from_file(key)) {
+            (Some(shorthand), _) => vec![shorthand],
+            (None, Some(patterns)) => patterns.clone(),
+            (None, None) => default.iter().map(|p| p.to_string()).collect(),
+        };
+        new(&patterns).map_err(|e| anyhow::anyhow!("{} = {:?}: {}", key, patterns.join(" "), e))
+    };
+    let python_classes = patterns("python_classes", Patterns::new, None, &["Test"])?;
+    let python_files = patterns(
+        "python_files",
+        Patterns::files,
+        matches
+            .get_one::<String>("file_prefix")
+            .map(|prefix| format!("{}*.py", prefix)),
+        &["test_*.py", "*_test.py"],
+    )?;
+    let python_functions = patterns(
+        "python_functions",
+        Patterns::new,
+        matches
+            .get_one::<String>("test_prefix")
+            .map(|prefix| format!("{}*", prefix)),
+        &["test"],
+    )?;
+
+    let async_backend = match (
+        matches.get_one::<String>("async_backend"),
+        from_file("async_backend"),
+    ) {
+        (Some(backend), _) => backend.to_string(),
+        (None, Some(backend)) if ASYNC_BACKENDS.contains(&backend[0].as_str()) => {
+            backend[0].to_string()
+        }
+        (None, Some(backend)) => anyhow::bail!(
+            "async_backend = {:?}: expected one of {}",
+            backend[0],
+            ASYNC_BACKENDS.join(", ")
+        ),
+        (None, None) => "asyncio".to_string(),
+    };
+
+    let junit_family = match from_file("junit_family") {
+        Some(family) if JUNIT_FAMILIES.contains(&family[0].as_str()) => family[0].to_string(),
+        Some(family) => anyhow::bail!(
+            "junit_family = {:?}: expected one of {}",
+            family[0],
+            JUNIT_FAMILIES.join(", ")
+        ),
+        None => "xunit2".to_string(),
+    };
+
+    Ok(Config {
+        async_backend,
+        cache_clear: matches.get_flag("cache_clear"),
+        cache_dir: Some(match from_file("cache_dir") {
+            Some(cache_dir) => rootdir.join(&cache_dir[0]),
+            None => rootdir.join(".rytest_cache"),
+        }),
+        cancellation: Cancellation::default(),
+        collect_only: matches.get_flag("collect_only"),
+        color: match matches.get_one::<String>("color").map(|s| s.as_str()) {
+            Some("yes") => Some(true),
+            Some("no") => Some(false),
+            _ => None,
+        },
+        collect_threads: matches
+            .get_one::<usize>("collect_threads")
+            .copied()
+            .unwrap_or_default(),
+        continue_on_collection_errors: matches.get_flag("continue_on_collection_errors"),
+        deselect: matches
+            .get_many::<String>("deselect")
+            .unwrap_or_default()
+            .map(|s| to_node_id(s, &current_dir, &rootdir))
+            .collect(),
+        doctest_globs: match matches.get_many::<String>("doctest_glob") {
+            Some(globs) => globs.map(|s| s.to_string()).collect(),
+            None => vec!["test*.txt".to_string()],
+        },
+        doctest_modules: matches.get_flag("doctest_modules"),
+        doctest_optionflags: match from_file("doctest_optionflags") {
+            Some(flags) => flags.clone(),
+            None => vec!["ELLIPSIS".to_string()],
+        },
+        durations: matches.get_one::<usize>("durations").copied(),
+        durations_min: Duration::try_from_secs_f64(
+            matches
+                .get_one::<f64>("durations_min")
+                .copied()
+                .unwrap_or(0.005),
+        )
+        .map_err(|e| anyhow::anyhow!("Wrong value passed to '--durations-min': {}", e))?,
+        dynamic_collection: matches.get_flag("dynamic_collection"),
+        files,
         ignores: matches
             .get_many::<String>("ignore")
             .unwrap()
             .map(|s| s.to_string())
             .collect(),
+        ignore_globs: matches
+            .get_many::<String>("ignore_glob")
+            .unwrap_or_default()
+            .map(|s| s.to_string())
+            .collect(),
+        inifile: file_config.as_ref().map(|c| c.path.clone()),
         info: matches.get_flag("info"),
-        test_prefix: matches
-            .get_one::<String>("test_prefix")
-            .unwrap()
-            .to_string(),
-        verbose: matches.get_flag("verbose"),
+        junit_family,
+        junitxml: matches.get_one::<PathBuf>("junitxml").cloned(),
+        keyword: match matches.get_one::<String>("keyword") {
+            Some(expr) if !expr.trim().is_empty() => Some(
+                Expression::parse(expr)
+                    .map_err(|e| anyhow::anyhow!("Wrong expression passed to '-k': {}", e))?,
+            ),
+            _ => None,
+        },
+        markers: from_file("markers").cloned().unwrap_or_default(),
+        markexpr: match matches.get_one::<String>("markexpr") {
+            Some(expr) if !expr.trim().is_empty() => Some(
+                Expression::parse(expr)
+                    .map_err(|e| anyhow::anyhow!("Wrong expression passed to '-m': {}", e))?,
+            ),
+            _ => None,
+        },
+        maxfail: match matches.get_flag("exitfirst") {
+            true => 1,
+            false => matches.get_one::<usize>("maxfail").copied().unwrap_or(0),
+        },
+        node_ids,
+        norecursedirs: match from_file("norecursedirs") {
+            Some(norecursedirs) => norecursedirs.clone(),
+            None => DEFAULT_NORECURSEDIRS
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+        },
+        python_classes,
+        python_files,
+        python_functions,
+        pythonpath: from_file("pythonpath")
+            .map(|paths| {
+                paths
+                    .iter()
+                    .map(|p| file_config.as_ref().unwrap().dir().join(p))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        report_chars: report_chars(
+            matches
+                .get_one::<String>("report_chars")
+                .map_or("fE", |s| s.as_str()),
+        )?,
+        report_json: matches.get_one::<PathBuf>("report_json").cloned(),
+        report_jsonl: matches.get_one::<String>("report_jsonl").cloned(),
+        respect_gitignore: matches.get_flag("respect_gitignore"),
+        rootdir,
+        strict_markers: matches.get_flag("strict_markers"),
+        verbosity: matches.get_count("verbose") as i8 - matches.get_count("quiet") as i8,
+    })
+}
+
+/// Parses the command line, exiting with pytest's usage error code if it's
+/// invalid, or once help or the version has been printed.
+fn get_matches(args: Vec<String>) -> ArgMatches {
+    command().try_get_matches_from(args).unwrap_or_else(|e| {
+        if e.use_stderr() {
+            let _ = e.print();
+            process::exit(ExitCode::UsageError as i32);
+        }
+        e.exit()
     })
 }
 
-pub fn run(config: Config) -> Result<()> {
+/// Expands the characters given to `-r` the way pytest does, where `a`, `A`
+/// and `N` replace everything before them and `w` is accepted but ignored.
+fn report_chars(chars: &str) -> Result<String> {
+    let mut expanded = String::new();
+    for c in chars.chars() {
+        match c {
+            'a' => expanded = "sxXEf".to_string(),
+            'A' => expanded = "PpsxXEf".to_string(),
+            'N' => expanded.clear(),
+            // Warnings aren't captured, so there are none to list.
+            'w' => {}
+            c if !REPORT_CHARS.contains(c) => anyhow::bail!(
+                "Unknown character {:?} passed to '-r', expected some of {}waAN",
+                c,
+                REPORT_CHARS
+            ),
+            c if !expanded.contains(c) => expanded.push(c),
+            _ => {}
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Splits a command line argument like `tests/test_file.py::test_name` into
+/// the path to search and, if present, the node ID to select.
+fn split_node_id(arg: &str, current_dir: &Path, rootdir: &Path) -> (String, Option<String>) {
+    match arg.split_once("::") {
+        Some((path, _)) => {
+            let path = path.strip_prefix("./").unwrap_or(path);
+            (
+                path.to_string(),
+                Some(to_node_id(arg, current_dir, rootdir)),
+            )
+        }
+        None => (arg.to_string(), None),
+    }
+}
+
+/// Rewrites the path in a node ID given on the command line to be relative to
+/// the rootdir, which is how collected tests are identified.
+fn to_node_id(arg: &str, current_dir: &Path, rootdir: &Path) -> String {
+    let (path, rest) = match arg.split_once("::") {
+        Some((path, rest)) => (path, Some(rest)),
+        None => (arg, None),
+    };
+    let path = config::normalize_path(&current_dir.join(path));
+    let path = path
+        .strip_prefix(rootdir)
+        .unwrap_or(&path)
+        .to_string_lossy();
+    match rest {
+        Some(rest) => format!("{}::{}", path, rest),
+        None => path.to_string(),
+    }
+}
+
+/// Expresses `path` relative to `dir` where possible, so paths from a
+/// configuration file read the same as ones given on the command line.
+fn relative_to(path: &Path, dir: &Path) -> String {
+    path.strip_prefix(dir)
+        .map(|p| {
+            if p.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                p
+            }
+        })
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Runs the tests, returning the exit code of the run.
+pub fn run(config: Config) -> Result<ExitCode> {
+    let reporters = reporting::reporters(&config)?;
+    run_with_reporters(config, reporters)
+}
+
+/// Runs the tests, passing what happens on to the given reporters rather than
+/// those the configuration asks for, so callers can add their own.
+pub fn run_with_reporters(config: Config, reporters: Vec<Box<dyn Reporter>>) -> Result<ExitCode> {
     let start = Instant::now();
 
+    if let Some(color) = config.color {
+        colored::control::set_override(color);
+    }
+
     if config.info {
-        info()?;
+        info(&config)?;
+    }
+
+    if config.cache_clear {
+        cache::clear(&config)?;
     }
 
     let (tx_files, rx_files) = mpsc::channel();
     let (tx_tests, rx_tests) = mpsc::channel();
 
-    let _ = thread::spawn(move || {
+    let files_config = config.clone();
+    let files = thread::spawn(move || {
         let tx_files = tx_files.clone();
-        collection::find_files(
-            config.files,
-            config.ignores,
-            config.file_prefix.as_str(),
-            tx_files,
-        )
-        .unwrap();
+        collection::find_files(&files_config, tx_files)
     });
 
-    let _ = thread::spawn(move || {
+    let tests_config = config.clone();
+    let tests = thread::spawn(move || {
         let tx_tests = tx_tests.clone();
-        collection::find_tests(
-            config.test_prefix.clone(),
-            config.verbose,
-            rx_files,
-            tx_tests,
-        )
-        .unwrap();
+        collection::find_tests(&tests_config, rx_files, tx_tests)
     });
 
-    if !config.collect_only {
-        let (tx_results, rx_results) = mpsc::channel();
+    // collected tests are reported as they're passed on to be run, so
+    // reporters know how many there are before they've all finished. Unless
+    // collection errors are allowed, nothing runs until every test has been
+    // collected without any.
+    let (tx_results, rx_results) = mpsc::channel();
+    let (tx_run, rx_run) = mpsc::channel();
+    let tx_collected = tx_results.clone();
+    let collect_only = config.collect_only;
+    let continue_on_errors = config.continue_on_collection_errors;
+    let (cancellation, maxfail) = (config.cancellation.clone(), config.maxfail);
+    let tee = thread::spawn(move || -> Result<()> {
+        let mut errors = 0;
+        let mut held = vec![];
+        for test in rx_tests {
+            // once cancelled, the rest of the tests are drained unreported
+            if cancellation.is_cancelled() {
+                continue;
+            }
+            if test.error.is_some() {
+                errors += 1;
+                tx_collected.send(Event::Collected(test))?;
+                if continue_on_errors && !collect_only && cancellation.fail(maxfail) {
+                    tx_collected.send(Event::Stopped(format!(
+                        "stopping after {} failures",
+                        maxfail
+                    )))?;
+                }
+                continue;
+            }
 
-        let _ = thread::spawn(move || {
-            let tx_results = tx_results.clone();
-            execution::run_tests(rx_tests, tx_results).unwrap();
-        });
+            tx_collected.send(Event::Collected(test.without_result()))?;
+            if collect_only {
+                continue;
+            }
+            if continue_on_errors {
+                tx_run.send(test)?;
+            } else {
+                held.push(test);
+            }
+        }
+        tx_collected.send(Event::CollectionFinished)?;
 
-        let handle_output = thread::spawn(move || {
-            let rx_results = rx_results;
-            reporting::output_results(rx_results, start).unwrap();
-        });
-        handle_output.join().unwrap();
+        if errors > 0 && !continue_on_errors {
+            let plural = if errors == 1 { "" } else { "s" };
+            tx_collected.send(Event::Interrupted(format!(
+                "Interrupted: {} error{} during collection",
+                errors, plural
+            )))?;
+            return Ok(());
+        }
+        for test in held {
+            if cancellation.is_cancelled() {
+                break;
+            }
+            tx_run.send(test)?;
+        }
+        Ok(())
+    });
+
+    let mut workers = vec![files, tests, tee];
+    if !collect_only {
+        let tests_config = config.clone();
+        workers.push(thread::spawn(move || {
+            execution::run_tests(&tests_config, rx_run, tx_results)
+        }));
     } else {
-        let handle_output = thread::spawn(move || {
-            reporting::output_collect(rx_tests, start).unwrap();
-        });
-        handle_output.join().unwrap();
+        drop(tx_results);
     }
 
-    Ok(())
+    let handle_output = thread::spawn(move || reporting::report(rx_results, reporters, start));
+    let exit_code = handle_output
+        .join()
+        .map_err(|_| anyhow::anyhow!("reporting panicked"))??;
+
+    // a thread that stopped because the one it feeds did, such as reporting
+    // to a pipe that was closed, has nothing more to report
+    let mut failed = false;
+    for worker in workers {
+        match worker.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) if disconnected(&e) => {}
+            Ok(Err(e)) => {
+                eprintln!("INTERNALERROR> {}", e);
+                failed = true;
+            }
+            // the panic has already been printed
+            Err(_) => failed = true,
+        }
+    }
+
+    Ok(if failed {
+        ExitCode::InternalError
+    } else {
+        exit_code
+    })
 }
 
-fn info() -> Result<()> {
+/// Whether an error is from sending to a channel whose receiving end was
+/// dropped.
+fn disconnected(e: &anyhow::Error) -> bool {
+    e.is::<mpsc::SendError<String>>()
+        || e.is::<mpsc::SendError<TestCase>>()
+        || e.is::<mpsc::SendError<Event>>()
+}
+
+fn info(config: &Config) -> Result<()> {
     println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
-    let pyinfo = python::get_info()?;
+    println!("Rootdir: {}", config.rootdir.display());
+    match &config.inifile {
+        Some(inifile) => println!("Config file: {}", inifile.display()),
+        None => println!("Config file: none"),
+    }
+    let pyinfo = python::get_info(config)?;
 
     println!("Python executable: {:?}", pyinfo.executable);
     println!("Python version: {}", pyinfo.version);
@@ -1,8 +1,19 @@
 // Include the clap crate for command line argument parsing
 
+use rytest::ExitCode;
+
 fn main() {
-    if let Err(e) = rytest::get_args().and_then(rytest::run) {
+    let config = match rytest::get_args() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(ExitCode::UsageError as i32);
+        }
+    };
+
+    let exit_code = rytest::run(config).unwrap_or_else(|e| {
         eprintln!("{}", e);
-        std::process::exit(1);
-    }
+        ExitCode::InternalError
+    });
+    std::process::exit(exit_code as i32);
 }
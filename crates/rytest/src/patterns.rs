@@ -0,0 +1,98 @@
+use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Name patterns as accepted by pytest's `python_files`, `python_classes`
+/// and `python_functions`, e.g. `test_*.py *_test.py`. Patterns containing
+/// wildcards are globs, anything else matches as a prefix, except for file
+/// patterns which always match the whole name.
+#[derive(Debug, Clone, Default)]
+pub struct Patterns {
+    patterns: Vec<String>,
+    prefixes: Vec<String>,
+    globs: GlobSet,
+}
+
+impl Patterns {
+    pub fn new(patterns: &[String]) -> Result<Patterns> {
+        let mut prefixes = vec![];
+        let mut globs = GlobSetBuilder::new();
+        for pattern in patterns {
+            if pattern.contains(['*', '?', '[']) {
+                globs.add(Glob::new(pattern)?);
+            } else {
+                prefixes.push(pattern.to_string());
+            }
+        }
+
+        Ok(Patterns {
+            patterns: patterns.to_vec(),
+            prefixes,
+            globs: globs.build()?,
+        })
+    }
+
+    /// Patterns for `python_files`, which pytest matches with `fnmatch`, so
+    /// `check.py` matches only `check.py`.
+    pub fn files(patterns: &[String]) -> Result<Patterns> {
+        let mut globs = GlobSetBuilder::new();
+        for pattern in patterns {
+            globs.add(Glob::new(pattern)?);
+        }
+
+        Ok(Patterns {
+            patterns: patterns.to_vec(),
+            prefixes: vec![],
+            globs: globs.build()?,
+        })
+    }
+
+    /// The patterns as they were configured.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.prefixes.iter().any(|prefix| name.starts_with(prefix)) || self.globs.is_match(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Patterns {
+        Patterns::new(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn it_matches_globs() {
+        let files = patterns(&["test_*.py", "*_test.py"]);
+        assert!(files.is_match("test_file.py"));
+        assert!(files.is_match("file_test.py"));
+        assert!(!files.is_match("file.py"));
+        assert!(!files.is_match("test_file.pyc"));
+    }
+
+    #[test]
+    fn it_matches_prefixes() {
+        let functions = patterns(&["test", "check_*"]);
+        assert!(functions.is_match("test_function"));
+        assert!(functions.is_match("testfunction"));
+        assert!(functions.is_match("check_function"));
+        assert!(!functions.is_match("checkfunction"));
+    }
+
+    #[test]
+    fn it_matches_whole_file_names() {
+        let files = Patterns::files(&["check.py".to_string(), "test_*.py".to_string()]).unwrap();
+        assert!(files.is_match("check.py"));
+        assert!(!files.is_match("check.py.bak"));
+        assert!(!files.is_match("check.pyi"));
+        assert!(files.is_match("test_file.py"));
+    }
+
+    #[test]
+    fn it_rejects_invalid_globs() {
+        assert!(Patterns::new(&["test_[.py".to_string()]).is_err());
+    }
+}
@@ -1,44 +1,103 @@
 use anyhow::Result;
-use pyo3::exceptions::PySyntaxError;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use pyo3::exceptions::{PySyntaxError, PyValueError};
 use pyo3::PyErr;
-use rustpython_parser::ast::Stmt::{self, ClassDef, FunctionDef};
-use rustpython_parser::{ast, Parse};
-use std::io::Read;
-use std::{fs::File, sync::mpsc};
-use walkdir::WalkDir;
+use rayon::ThreadPoolBuilder;
+use rustpython_parser::ast::Stmt::{self, AsyncFunctionDef, ClassDef, FunctionDef};
+use rustpython_parser::{ast, Parse, ParseError};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::{env, fs, sync::mpsc, thread};
 
-use crate::TestCase;
+use crate::cache::{CacheUpdate, CollectionCache, Fingerprint};
+use crate::config::normalize_path;
+use crate::{Config, Marker, Patterns, TestCase};
 
 use crate::phases::collectors::ignore_test;
 
-use super::collectors::ignore_skip;
+use super::collectors::blocks::statements;
+use super::collectors::doctests;
+use super::collectors::markers;
 use super::collectors::parametrize;
+use super::collectors::symbols::SymbolTable;
 use super::execution;
 
-pub fn find_files(
-    paths: Vec<String>,
-    ignores: Vec<String>,
-    prefix: &str,
-    tx: mpsc::Sender<String>,
-) -> Result<()> {
-    for path in &paths {
-        for entry in WalkDir::new(path)
-            .sort_by_file_name()
-            .into_iter()
+/// Walks `config.files` for test files, pruning directories that are
+/// ignored, match `norecursedirs` or contain a virtualenv as it goes, unless
+/// they were given explicitly.
+pub fn find_files(config: &Config, tx: mpsc::Sender<String>) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let ignores: Vec<PathBuf> = config
+        .ignores
+        .iter()
+        .map(|i| normalize_path(&current_dir.join(i)))
+        .collect();
+
+    let mut ignore_globs = GlobSetBuilder::new();
+    for pattern in &config.ignore_globs {
+        // like pytest, patterns are matched against absolute paths
+        let pattern = normalize_path(&current_dir.join(pattern));
+        ignore_globs.add(Glob::new(&pattern.to_string_lossy())?);
+    }
+    let ignore_globs = ignore_globs.build()?;
+
+    let mut norecurse = GlobSetBuilder::new();
+    for pattern in &config.norecursedirs {
+        norecurse.add(Glob::new(pattern)?);
+    }
+    let norecurse = norecurse.build()?;
+
+    let mut doctest_globs = GlobSetBuilder::new();
+    for pattern in &config.doctest_globs {
+        doctest_globs.add(Glob::new(pattern)?);
+    }
+    let doctest_globs = doctest_globs.build()?;
+
+    for path in &config.files {
+        let (walk_dir, walk_ignores, walk_ignore_globs, walk_norecurse) = (
+            current_dir.clone(),
+            ignores.clone(),
+            ignore_globs.clone(),
+            norecurse.clone(),
+        );
+        let walker = WalkBuilder::new(path)
+            .standard_filters(false)
+            .git_ignore(config.respect_gitignore)
+            .git_exclude(config.respect_gitignore)
+            .ignore(config.respect_gitignore)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(move |e| {
+                if e.depth() == 0 || !e.file_type().is_some_and(|t| t.is_dir()) {
+                    return true;
+                }
+                let p = normalize_path(&walk_dir.join(e.path()));
+                !is_ignored(&p, &walk_ignores, &walk_ignore_globs)
+                    && !walk_norecurse.is_match(e.file_name())
+                    && !e.path().join("pyvenv.cfg").is_file()
+            })
+            .build();
+
+        for entry in walker
             .filter_map(Result::ok)
-            .filter(|e| !e.file_type().is_dir())
+            .filter(|e| !e.file_type().is_some_and(|t| t.is_dir()))
         {
-            let p = if matches!(path.as_str(), "." | "./") {
-                entry.path().strip_prefix("./")?
-            } else {
-                entry.path()
-            };
+            if config.cancellation.is_cancelled() {
+                return Ok(());
+            }
+            let p = normalize_path(&current_dir.join(entry.path()));
+            let file_name = p.file_name().unwrap().to_string_lossy();
+            let is_python = p.extension().is_some_and(|e| e == "py");
 
-            if p.file_stem().unwrap().to_string_lossy().starts_with(prefix)
-                && p.extension().unwrap() == "py"
-                && !ignores.iter().any(|i| p.to_str().unwrap().starts_with(i))
+            let doctest_module = config.doctest_modules && !is_setup_or_main(&p, &file_name);
+            if ((is_python && (config.python_files.is_match(&file_name) || doctest_module))
+                || doctest_globs.is_match(file_name.as_ref()))
+                && !is_ignored(&p, &ignores, &ignore_globs)
             {
-                tx.send(p.to_str().unwrap().to_string())?;
+                // files outside the rootdir keep their absolute path
+                let p = p.strip_prefix(&config.rootdir).unwrap_or(&p);
+                tx.send(p.to_string_lossy().to_string())?;
             }
         }
     }
@@ -48,122 +107,548 @@ pub fn find_files(
     Ok(())
 }
 
+fn is_ignored(path: &Path, ignores: &[PathBuf], ignore_globs: &GlobSet) -> bool {
+    ignores.iter().any(|i| path.starts_with(i)) || ignore_globs.is_match(path)
+}
+
+/// Checks for modules pytest won't import for doctests, since importing them
+/// runs them: a `setup.py` using setuptools or distutils, and `__main__.py`.
+fn is_setup_or_main(path: &Path, file_name: &str) -> bool {
+    match file_name {
+        "__main__.py" => true,
+        "setup.py" => fs::read_to_string(path)
+            .is_ok_and(|source| source.contains("setuptools") || source.contains("distutils")),
+        _ => false,
+    }
+}
+
 pub fn find_tests(
-    prefix: String,
-    verbose: bool,
+    config: &Config,
     rx: mpsc::Receiver<String>,
     tx: mpsc::Sender<TestCase>,
 ) -> Result<()> {
-    while let Ok(file_name) = rx.recv() {
-        let mut data = String::new();
-        let mut file = File::open(file_name.clone())?;
-        file.read_to_string(&mut data)?;
-        let ast = ast::Suite::parse(data.as_str(), "<embedded>");
-
-        match ast {
-            Ok(ast) => {
-                for stmt in ast {
-                    match stmt {
-                        FunctionDef(ref node)
-                            if node.name.starts_with(&prefix)
-                                && !ignore_skip::is_pytest_skip(&stmt)
-                                && !ignore_test::is_pytest_fixture(&stmt) =>
-                        {
-                            if parametrize::is_parametrized(&stmt) {
-                                match execution::get_parametrizations(&file_name, &node.name) {
-                                    Ok(parameters) => {
-                                        for param in parameters {
-                                            tx.send(TestCase {
-                                                file: file_name.clone(),
-                                                name: format!("{}[{}]", node.name, param),
-                                                passed: false,
-                                                error: None,
-                                                parametrized: true,
-                                            })?;
-                                        }
-                                    }
-                                    Err(e) => tx.send(TestCase {
-                                        file: file_name.clone(),
-                                        name: node.name.to_string(),
-                                        passed: false,
-                                        error: Some(e),
-                                        parametrized: true,
-                                    })?,
-                                }
-                            } else {
-                                tx.send(TestCase {
-                                    file: file_name.clone(),
-                                    name: node.name.to_string(),
-                                    passed: false,
-                                    error: None,
-                                    parametrized: false,
-                                })?;
-                            }
+    // node IDs given on the command line that selected at least one test, or
+    // whose file couldn't be parsed, so the rest can be reported as not found
+    let mut matched = HashSet::new();
+
+    let mut cache = CollectionCache::load(config);
+    let mut updates = vec![];
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(config.collect_threads)
+        .build()?;
+    let (tx_parsed, rx_parsed) = mpsc::channel();
+
+    thread::scope(|scope| -> Result<()> {
+        let (pool, cache) = (&pool, &cache);
+        scope.spawn(move || {
+            pool.in_place_scope(|pool_scope| {
+                for (index, file_name) in rx.iter().enumerate() {
+                    // files still on their way are drained once cancelled
+                    if config.cancellation.is_cancelled() {
+                        continue;
+                    }
+                    let tx_parsed = tx_parsed.clone();
+                    pool_scope.spawn(move |_| {
+                        let parsed = parse_file(&config.rootdir, cache, file_name);
+                        let _ = tx_parsed.send((index, parsed));
+                    });
+                }
+            });
+        });
+
+        // files finish parsing out of order, so hold on to them until every
+        // file before them in walk order has been collected
+        let mut parsed_files = BTreeMap::new();
+        let mut next = 0;
+        for (index, parsed) in rx_parsed {
+            if config.cancellation.is_cancelled() {
+                continue;
+            }
+            parsed_files.insert(index, parsed);
+            while let Some(parsed) = parsed_files.remove(&next) {
+                let parsed = parsed?;
+                let (tests, imported) = match parsed.contents {
+                    FileContents::Cached(tests) => (tests, false),
+                    FileContents::Parsed { source, ast } => {
+                        collect_file(config, &parsed.name, &source, ast)
+                    }
+                    FileContents::Text(source) => (collect_text_file(&parsed.name, &source), false),
+                };
+                updates.push(CacheUpdate::new(
+                    &parsed.name,
+                    &parsed.fingerprint,
+                    &tests,
+                    imported,
+                ));
+                send_tests(&mut matched, config, &tx, &parsed.name, tests)?;
+                next += 1;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    for update in updates {
+        cache.update(update);
+    }
+    if let Err(e) = cache.save(config) {
+        eprintln!("warning: could not write the collection cache: {}", e);
+    }
+
+    if config.cancellation.is_cancelled() {
+        return Ok(());
+    }
+    for node_id in config.node_ids.iter().filter(|id| !matched.contains(*id)) {
+        tx.send(TestCase {
+            error: Some(PyErr::new::<PyValueError, _>(format!(
+                "not found: {}",
+                node_id
+            ))),
+            ..TestCase::new(
+                node_id_file(node_id),
+                &node_id[node_id_file(node_id).len() + 2..],
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// A test file read on the collection thread pool, either parsed or with its
+/// tests taken from the collection cache.
+struct ParsedFile {
+    name: String,
+    fingerprint: Fingerprint,
+    contents: FileContents,
+}
+
+enum FileContents {
+    Cached(Vec<TestCase>),
+    Parsed {
+        source: String,
+        ast: Result<ast::Suite, ParseError>,
+    },
+    /// A text file matching `--doctest-glob`, which isn't parsed as Python.
+    Text(String),
+}
+
+fn parse_file(rootdir: &Path, cache: &CollectionCache, file_name: String) -> Result<ParsedFile> {
+    let path = rootdir.join(&file_name);
+    let fingerprint = Fingerprint::new(&fs::metadata(&path)?);
+    if let Some(tests) = cache.get(&file_name, &fingerprint) {
+        return Ok(ParsedFile {
+            name: file_name,
+            fingerprint,
+            contents: FileContents::Cached(tests),
+        });
+    }
+
+    let source = fs::read_to_string(&path)?;
+    let fingerprint = fingerprint.with_source(&source);
+    let contents = match cache.get(&file_name, &fingerprint) {
+        Some(tests) => FileContents::Cached(tests),
+        None if !file_name.ends_with(".py") => FileContents::Text(source),
+        None => {
+            let ast = ast::Suite::parse(source.as_str(), "<embedded>");
+            FileContents::Parsed { source, ast }
+        }
+    };
+
+    Ok(ParsedFile {
+        name: file_name,
+        fingerprint,
+        contents,
+    })
+}
+
+/// Sends on the tests collected from a file. Collection errors go straight
+/// through, and count as a match for any node ID in the file.
+fn send_tests(
+    matched: &mut HashSet<String>,
+    config: &Config,
+    tx: &mpsc::Sender<TestCase>,
+    file_name: &str,
+    tests: Vec<TestCase>,
+) -> Result<()> {
+    for test in tests {
+        if test.error.is_some() {
+            matched.extend(
+                config
+                    .node_ids
+                    .iter()
+                    .filter(|node_id| node_id_file(node_id) == file_name)
+                    .cloned(),
+            );
+            tx.send(test)?;
+        } else {
+            send_test(matched, config, tx, test)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the tests in a parsed file, before any selection is applied, and
+/// whether the module had to be imported to find them.
+fn collect_file(
+    config: &Config,
+    file_name: &str,
+    data: &str,
+    ast: Result<ast::Suite, ParseError>,
+) -> (Vec<TestCase>, bool) {
+    let mut tests = vec![];
+    let mut imported = false;
+    let functions = &config.python_functions;
+    let verbose = config.verbosity > 0;
+
+    match ast {
+        Ok(ast) => {
+            if config.doctest_modules {
+                let module = doctests::module_name(&config.rootdir.join(file_name));
+                tests.extend(
+                    doctests::find_doctests(&ast, &module)
+                        .into_iter()
+                        .map(|name| doctest(file_name, name)),
+                );
+                // modules are only searched for doctests unless they're test files
+                let base_name = Path::new(file_name).file_name().unwrap_or_default();
+                if !config.python_files.is_match(&base_name.to_string_lossy()) {
+                    return (tests, imported);
+                }
+            }
+
+            let symbols = SymbolTable::from_suite(&ast);
+            let module_markers = markers::get_pytestmark(&ast, &symbols, data);
+            // a name defined in several branches is only collected once
+            let mut seen = HashSet::new();
+            for stmt in statements(&ast) {
+                match stmt {
+                    FunctionDef(ast::StmtFunctionDef { name, .. })
+                    | AsyncFunctionDef(ast::StmtAsyncFunctionDef { name, .. })
+                        if functions.is_match(name)
+                            && !ignore_test::is_pytest_fixture(stmt, &symbols)
+                            && seen.insert(name.as_str()) =>
+                    {
+                        let test_markers = markers::get_markers(stmt, &symbols, data);
+                        tests.extend(function_tests(
+                            config,
+                            file_name,
+                            &ast,
+                            &mut imported,
+                            name,
+                            &[&module_markers, &test_markers],
+                        ));
+                    }
+                    ClassDef(node)
+                        if (node
+                            .bases
+                            .iter()
+                            .any(|base| find_unittest_base(base, &symbols))
+                            || is_test_class(node, &config.python_classes))
+                            && seen.insert(node.name.as_str()) =>
+                    {
+                        let cases = find_unittest_class_cases(
+                            node.body.clone(),
+                            functions,
+                            &symbols,
+                            file_name.to_string(),
+                            verbose,
+                        );
+                        let mut class_markers = markers::get_markers(stmt, &symbols, data);
+                        class_markers.extend(markers::get_pytestmark(&node.body, &symbols, data));
+
+                        for case in cases {
+                            let test_markers = statements(&node.body)
+                                .into_iter()
+                                .find(|method| function_name(method) == Some(case.as_str()))
+                                .map(|method| markers::get_markers(method, &symbols, data))
+                                .unwrap_or_default();
+
+                            tests.extend(function_tests(
+                                config,
+                                file_name,
+                                &ast,
+                                &mut imported,
+                                &format!("{}::{}", node.name, case),
+                                &[&module_markers, &class_markers, &test_markers],
+                            ));
+                        }
+                    }
+                    //_ if verbose => println!("{}: Skipping {:#?}\n\n", file_name, stmt),
+                    _ => {}
+                }
+            }
+
+            if config.dynamic_collection {
+                imported = true;
+                // functions and methods in the source were already considered
+                let mut defined = HashSet::new();
+                for stmt in statements(&ast) {
+                    if let Some(name) = function_name(stmt) {
+                        defined.insert(name.to_string());
+                    }
+                    if let ClassDef(node) = stmt {
+                        for method in statements(&node.body).into_iter().filter_map(function_name) {
+                            defined.insert(format!("{}::{}", node.name, method));
                         }
-                        ClassDef(node) if node.bases.iter().any(find_unittest_base) => {
-                            let cases = find_unittest_class_cases(
-                                node.body.clone(),
-                                &prefix,
-                                file_name.clone(),
-                                verbose,
-                            );
-                            if !cases.is_empty() {
-                                for case in cases {
-                                    let class = node.name.as_str();
-                                    tx.send(TestCase {
-                                        file: file_name.clone(),
-                                        name: format!("{}::{}", class, case),
-                                        passed: false,
-                                        error: None,
-                                        parametrized: false,
-                                    })?
+                    }
+                }
+
+                match execution::get_namespace_tests(config, file_name) {
+                    Ok(found) => {
+                        for (class, unittest, name) in found {
+                            let name = match class {
+                                Some(class)
+                                    if unittest || config.python_classes.is_match(&class) =>
+                                {
+                                    format!("{}::{}", class, name)
                                 }
+                                Some(_) => continue,
+                                None => name,
+                            };
+                            let (_, function) = name.rsplit_once("::").unwrap_or(("", &name));
+                            if !functions.is_match(function) || defined.contains(&name) {
+                                continue;
                             }
+                            tests.extend(function_tests(
+                                config,
+                                file_name,
+                                &ast,
+                                &mut imported,
+                                &name,
+                                &[&module_markers],
+                            ));
                         }
-                        //_ if verbose => println!("{}: Skipping {:#?}\n\n", file_name, stmt),
-                        _ => {}
+                    }
+                    // the module can't be imported, so none of its tests can run
+                    Err(e) => {
+                        tests = vec![TestCase {
+                            error: Some(e),
+                            ..TestCase::new(file_name, "")
+                        }]
                     }
                 }
             }
-            Err(e) => tx.send(TestCase {
-                file: file_name.clone(),
-                name: "".to_string(),
-                passed: false,
-                error: Some(PyErr::new::<PySyntaxError, _>(format!(
-                    " Error parsing {}",
-                    e
-                ))),
-                parametrized: false,
-            })?,
         }
+        Err(e) => tests.push(TestCase {
+            error: Some(PyErr::new::<PySyntaxError, _>(format!(
+                " Error parsing {}",
+                e
+            ))),
+            ..TestCase::new(file_name, "")
+        }),
+    }
+
+    (tests, imported)
+}
+
+/// Finds the doctest in a text file, named after the file itself.
+fn collect_text_file(file_name: &str, source: &str) -> Vec<TestCase> {
+    if !doctests::has_text_examples(source) {
+        return vec![];
+    }
+    let base_name = Path::new(file_name).file_name().unwrap_or_default();
+    vec![doctest(file_name, base_name.to_string_lossy().to_string())]
+}
+
+fn doctest(file_name: &str, name: String) -> TestCase {
+    TestCase {
+        doctest: true,
+        ..TestCase::new(file_name, &name)
+    }
+}
+
+/// Builds the tests for a function or method from the markers it inherits,
+/// given outermost scope first, expanding it into one test per parameter set
+/// if any of them is `parametrize`. `imported` is set if the module had to
+/// be run to work out the parameters.
+fn function_tests(
+    config: &Config,
+    file_name: &str,
+    suite: &[Stmt],
+    imported: &mut bool,
+    name: &str,
+    scopes: &[&[Marker]],
+) -> Vec<TestCase> {
+    // the closest markers come first, as pytest lists them, so the
+    // `parametrize` markers are outermost first when reversed
+    let test_markers: Vec<Marker> = scopes
+        .iter()
+        .flat_map(|m| m.iter())
+        .rev()
+        .cloned()
+        .collect();
+    if !parametrize::is_parametrized(&test_markers) {
+        return vec![TestCase {
+            markers: test_markers,
+            ..TestCase::new(file_name, name)
+        }];
+    }
+
+    let parametrize = parametrize::outermost_first(&test_markers);
+    // only run the module when the parameters can't be worked out from the
+    // source
+    let parameters = match parametrize::get_parametrizations(&parametrize, suite) {
+        Some(parameters) => Ok(parameters),
+        None => {
+            *imported = true;
+            execution::get_parametrizations(config, file_name, &parametrize)
+        }
+    };
+
+    match parameters {
+        Ok(parameters) => parameters
+            .into_iter()
+            .map(|param| TestCase {
+                parametrized: true,
+                markers: test_markers.clone(),
+                ..TestCase::new(file_name, &format!("{}[{}]", name, param))
+            })
+            .collect(),
+        Err(e) => vec![TestCase {
+            error: Some(e),
+            parametrized: true,
+            markers: test_markers,
+            ..TestCase::new(file_name, name)
+        }],
+    }
+}
+
+/// Sends a collected test on, flagging it as deselected if it doesn't match
+/// `-m` or `-k`. Tests using unregistered markers are turned into errors under
+/// `--strict-markers`.
+fn send_test(
+    matched: &mut HashSet<String>,
+    config: &Config,
+    tx: &mpsc::Sender<TestCase>,
+    mut test: TestCase,
+) -> Result<()> {
+    let node_id = format!("{}::{}", test.file, test.name);
+
+    // node IDs narrow the tests collected from their file
+    let mut targeted = config
+        .node_ids
+        .iter()
+        .filter(|id| node_id_file(id) == test.file)
+        .peekable();
+    if targeted.peek().is_some() {
+        let selected_by: Vec<&String> = targeted
+            .filter(|id| matches_node_id(&node_id, id))
+            .collect();
+        if selected_by.is_empty() {
+            return Ok(());
+        }
+        matched.extend(selected_by.into_iter().cloned());
     }
 
+    if config.strict_markers {
+        let unknown = test.markers.iter().find(|marker| {
+            !markers::BUILTIN_MARKERS.contains(&marker.name.as_str())
+                && !config
+                    .markers
+                    .iter()
+                    .any(|registered| markers::registered_name(registered) == marker.name)
+        });
+        if let Some(marker) = unknown {
+            test.error = Some(PyErr::new::<PyValueError, _>(format!(
+                "'{}' not found in `markers` configuration option",
+                marker.name
+            )));
+            tx.send(test)?;
+            return Ok(());
+        }
+    }
+
+    test.deselected |= config
+        .deselect
+        .iter()
+        .any(|id| matches_node_id(&node_id, id));
+
+    if let Some(markexpr) = &config.markexpr {
+        test.deselected |=
+            !markexpr.evaluate(&|name| test.markers.iter().any(|marker| marker.name == name));
+    }
+
+    if let Some(keyword) = &config.keyword {
+        let keywords = keywords(&test);
+        test.deselected |= !keyword.evaluate(&|name| {
+            let name = name.to_lowercase();
+            keywords.iter().any(|keyword| keyword.contains(&name))
+        });
+    }
+
+    tx.send(test)?;
     Ok(())
 }
 
-fn find_unittest_base(expr: &ast::Expr) -> bool {
-    if expr.is_attribute_expr() {
-        let attr_expr = expr.as_attribute_expr().unwrap();
-        let module = attr_expr.value.as_name_expr().unwrap().id.as_str();
-        module == "unittest" && attr_expr.attr.as_str() == "TestCase"
-    } else {
-        false
+fn node_id_file(node_id: &str) -> &str {
+    node_id.split("::").next().unwrap_or(node_id)
+}
+
+/// Checks whether a test's node ID is selected by `selector`, which may name
+/// the test itself, its class, or a parametrized function as a whole.
+fn matches_node_id(node_id: &str, selector: &str) -> bool {
+    match node_id.strip_prefix(selector) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::") || rest.starts_with('['),
+        None => false,
+    }
+}
+
+/// The lowercased names `-k` matches against: the module file name, any
+/// class, the test itself including its parametrization ID, and its markers.
+fn keywords(test: &TestCase) -> Vec<String> {
+    let mut keywords = vec![];
+    if let Some(module) = Path::new(&test.file).file_name() {
+        keywords.push(module.to_string_lossy().to_string());
+    }
+    keywords.extend(test.name.split("::").map(|part| part.to_string()));
+    keywords.extend(test.markers.iter().map(|marker| marker.name.clone()));
+    keywords
+        .iter()
+        .map(|keyword| keyword.to_lowercase())
+        .collect()
+}
+
+fn find_unittest_base(expr: &ast::Expr, symbols: &SymbolTable) -> bool {
+    symbols.resolve(expr).as_deref() == Some("unittest.TestCase")
+}
+
+/// Plain classes are collected if they match `python_classes`, `Test` by
+/// default, as long as they don't define an `__init__`.
+fn is_test_class(node: &ast::StmtClassDef, python_classes: &Patterns) -> bool {
+    python_classes.is_match(&node.name)
+        && !node.body.iter().any(|stmt| match stmt {
+            FunctionDef(function) => function.name.as_str() == "__init__",
+            _ => false,
+        })
+}
+
+/// The name of a function or async function definition.
+fn function_name(stmt: &Stmt) -> Option<&str> {
+    match stmt {
+        FunctionDef(node) => Some(node.name.as_str()),
+        AsyncFunctionDef(node) => Some(node.name.as_str()),
+        _ => None,
     }
 }
 
 fn find_unittest_class_cases(
     stmts: Vec<Stmt>,
-    prefix: &str,
+    functions: &Patterns,
+    symbols: &SymbolTable,
     _file_name: String,
     _verbose: bool,
 ) -> Vec<String> {
     let mut cases = vec![];
-    for stmt in stmts {
+    for stmt in statements(&stmts) {
         match stmt {
-            FunctionDef(node)
-                if node.name.starts_with(prefix) && !ignore_test::is_pytest_fixture(&stmt) =>
+            FunctionDef(ast::StmtFunctionDef { name, .. })
+            | AsyncFunctionDef(ast::StmtAsyncFunctionDef { name, .. })
+                if functions.is_match(name)
+                    && !ignore_test::is_pytest_fixture(stmt, symbols)
+                    && !cases.iter().any(|case| case == name.as_str()) =>
             {
-                cases.push(node.name.to_string())
+                cases.push(name.to_string())
             }
             //_ if verbose => println!("{}: Skipping {:#?}\n\n", file_name, stmt),
             _ => {}
@@ -175,6 +660,7 @@ fn find_unittest_class_cases(
 #[cfg(test)]
 mod tests {
     use ast::{text_size::TextRange, EmptyRange, Expr, Identifier, TextSize};
+    use pyo3::indoc::indoc;
 
     #[cfg(test)]
     use pretty_assertions::assert_eq;
@@ -184,21 +670,38 @@ mod tests {
     #[test]
     fn test_find_files() {
         let paths = vec!["tests".to_string()];
-        let prefix = "test_".to_string();
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
         let (tx, rx) = mpsc::channel();
-        let _ = find_files(paths, vec![], prefix.as_str(), tx);
+        let config = Config {
+            files: paths,
+            ignores: vec![],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
         let mut files: Vec<String> = rx.iter().collect();
         files.sort();
 
         let mut expected = vec![
+            "tests/input/asyncio/test_async.py".to_string(),
+            "tests/input/capture/test_capture.py".to_string(),
             "tests/input/classes/test_classes.py".to_string(),
+            "tests/input/durations/test_durations.py".to_string(),
+            "tests/input/dynamic/test_conditional.py".to_string(),
             "tests/input/bad/test_other_error.py".to_string(),
             "tests/input/bad/test_other_file.py".to_string(),
             "tests/input/folder/test_another_file.py".to_string(),
             "tests/input/test_bad_file.py".to_string(),
             "tests/input/good/test_success.py".to_string(),
+            "tests/input/interrupt/test_interrupt.py".to_string(),
+            "tests/input/markers/test_markers.py".to_string(),
+            "tests/input/marks/test_class_marks.py".to_string(),
+            "tests/input/marks/test_module_skip.py".to_string(),
+            "tests/input/marks/test_pytestmark.py".to_string(),
             "tests/input/test_file.py".to_string(),
             "tests/input/test_fixtures.py".to_string(),
+            "tests/input/xunit/test_xunit.py".to_string(),
         ];
         expected.sort();
 
@@ -208,21 +711,38 @@ mod tests {
     #[test]
     fn test_find_files_no_dir() {
         let paths = vec![".".to_string()];
-        let prefix = "test_".to_string();
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
         let (tx, rx) = mpsc::channel();
-        let _ = find_files(paths, vec![".venv".to_string()], prefix.as_str(), tx);
+        let config = Config {
+            files: paths,
+            ignores: vec![".venv".to_string()],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
         let mut files: Vec<String> = rx.iter().collect();
         files.sort();
 
         let mut expected = vec![
+            "tests/input/asyncio/test_async.py".to_string(),
+            "tests/input/capture/test_capture.py".to_string(),
             "tests/input/classes/test_classes.py".to_string(),
+            "tests/input/durations/test_durations.py".to_string(),
+            "tests/input/dynamic/test_conditional.py".to_string(),
             "tests/input/bad/test_other_error.py".to_string(),
             "tests/input/bad/test_other_file.py".to_string(),
             "tests/input/folder/test_another_file.py".to_string(),
             "tests/input/test_bad_file.py".to_string(),
             "tests/input/good/test_success.py".to_string(),
+            "tests/input/interrupt/test_interrupt.py".to_string(),
+            "tests/input/markers/test_markers.py".to_string(),
+            "tests/input/marks/test_class_marks.py".to_string(),
+            "tests/input/marks/test_module_skip.py".to_string(),
+            "tests/input/marks/test_pytestmark.py".to_string(),
             "tests/input/test_file.py".to_string(),
             "tests/input/test_fixtures.py".to_string(),
+            "tests/input/xunit/test_xunit.py".to_string(),
         ];
         expected.sort();
 
@@ -232,21 +752,38 @@ mod tests {
     #[test]
     fn test_find_files_this_dir() {
         let paths = vec!["./".to_string()];
-        let prefix = "test_".to_string();
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
         let (tx, rx) = mpsc::channel();
-        let _ = find_files(paths, vec![".venv".to_string()], prefix.as_str(), tx);
+        let config = Config {
+            files: paths,
+            ignores: vec![".venv".to_string()],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
         let mut files: Vec<String> = rx.iter().collect();
         files.sort();
 
         let mut expected = vec![
+            "tests/input/asyncio/test_async.py".to_string(),
+            "tests/input/capture/test_capture.py".to_string(),
             "tests/input/classes/test_classes.py".to_string(),
+            "tests/input/durations/test_durations.py".to_string(),
+            "tests/input/dynamic/test_conditional.py".to_string(),
             "tests/input/bad/test_other_error.py".to_string(),
             "tests/input/bad/test_other_file.py".to_string(),
             "tests/input/folder/test_another_file.py".to_string(),
             "tests/input/test_bad_file.py".to_string(),
             "tests/input/good/test_success.py".to_string(),
+            "tests/input/interrupt/test_interrupt.py".to_string(),
+            "tests/input/markers/test_markers.py".to_string(),
+            "tests/input/marks/test_class_marks.py".to_string(),
+            "tests/input/marks/test_module_skip.py".to_string(),
+            "tests/input/marks/test_pytestmark.py".to_string(),
             "tests/input/test_file.py".to_string(),
             "tests/input/test_fixtures.py".to_string(),
+            "tests/input/xunit/test_xunit.py".to_string(),
         ];
         expected.sort();
 
@@ -259,9 +796,16 @@ mod tests {
             "tests/input/bad".to_string(),
             "tests/input/good".to_string(),
         ];
-        let prefix = "test_".to_string();
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
         let (tx, rx) = mpsc::channel();
-        let _ = find_files(paths, vec![], prefix.as_str(), tx);
+        let config = Config {
+            files: paths,
+            ignores: vec![],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
         let mut files: Vec<String> = rx.iter().collect();
         files.sort();
 
@@ -278,21 +822,38 @@ mod tests {
     #[test]
     fn test_find_files_specific_dir() {
         let paths = vec!["tests/input".to_string()];
-        let prefix = "test_".to_string();
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
         let (tx, rx) = mpsc::channel();
-        let _ = find_files(paths, vec![], prefix.as_str(), tx);
+        let config = Config {
+            files: paths,
+            ignores: vec![],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
         let mut files: Vec<String> = rx.iter().collect();
         files.sort();
 
         let mut expected = vec![
+            "tests/input/asyncio/test_async.py".to_string(),
+            "tests/input/capture/test_capture.py".to_string(),
             "tests/input/classes/test_classes.py".to_string(),
+            "tests/input/durations/test_durations.py".to_string(),
+            "tests/input/dynamic/test_conditional.py".to_string(),
             "tests/input/bad/test_other_error.py".to_string(),
             "tests/input/bad/test_other_file.py".to_string(),
             "tests/input/folder/test_another_file.py".to_string(),
             "tests/input/test_bad_file.py".to_string(),
             "tests/input/good/test_success.py".to_string(),
+            "tests/input/interrupt/test_interrupt.py".to_string(),
+            "tests/input/markers/test_markers.py".to_string(),
+            "tests/input/marks/test_class_marks.py".to_string(),
+            "tests/input/marks/test_module_skip.py".to_string(),
+            "tests/input/marks/test_pytestmark.py".to_string(),
             "tests/input/test_file.py".to_string(),
             "tests/input/test_fixtures.py".to_string(),
+            "tests/input/xunit/test_xunit.py".to_string(),
         ];
         expected.sort();
 
@@ -302,25 +863,37 @@ mod tests {
     #[test]
     fn test_find_files_ignore_file() {
         let paths = vec!["tests/input".to_string()];
-        let prefix = "test_".to_string();
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
         let (tx, rx) = mpsc::channel();
-        let _ = find_files(
-            paths,
-            vec!["tests/input/test_file.py".to_string()],
-            prefix.as_str(),
-            tx,
-        );
+        let config = Config {
+            files: paths,
+            ignores: vec!["tests/input/test_file.py".to_string()],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
         let mut files: Vec<String> = rx.iter().collect();
         files.sort();
 
         let mut expected = vec![
+            "tests/input/asyncio/test_async.py".to_string(),
+            "tests/input/capture/test_capture.py".to_string(),
             "tests/input/classes/test_classes.py".to_string(),
+            "tests/input/durations/test_durations.py".to_string(),
+            "tests/input/dynamic/test_conditional.py".to_string(),
             "tests/input/bad/test_other_error.py".to_string(),
             "tests/input/bad/test_other_file.py".to_string(),
             "tests/input/folder/test_another_file.py".to_string(),
             "tests/input/test_bad_file.py".to_string(),
             "tests/input/good/test_success.py".to_string(),
+            "tests/input/interrupt/test_interrupt.py".to_string(),
+            "tests/input/markers/test_markers.py".to_string(),
+            "tests/input/marks/test_class_marks.py".to_string(),
+            "tests/input/marks/test_module_skip.py".to_string(),
+            "tests/input/marks/test_pytestmark.py".to_string(),
             "tests/input/test_fixtures.py".to_string(),
+            "tests/input/xunit/test_xunit.py".to_string(),
         ];
         expected.sort();
 
@@ -330,30 +903,287 @@ mod tests {
     #[test]
     fn test_find_files_ignore_folder() {
         let paths = vec!["tests/input".to_string()];
-        let prefix = "test_".to_string();
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
         let (tx, rx) = mpsc::channel();
-        let _ = find_files(
-            paths,
-            vec!["tests/input/bad".to_string()],
-            prefix.as_str(),
-            tx,
-        );
+        let config = Config {
+            files: paths,
+            ignores: vec!["tests/input/bad".to_string()],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
         let mut files: Vec<String> = rx.iter().collect();
         files.sort();
 
         let mut expected = vec![
+            "tests/input/asyncio/test_async.py".to_string(),
+            "tests/input/capture/test_capture.py".to_string(),
             "tests/input/classes/test_classes.py".to_string(),
+            "tests/input/durations/test_durations.py".to_string(),
+            "tests/input/dynamic/test_conditional.py".to_string(),
             "tests/input/folder/test_another_file.py".to_string(),
             "tests/input/good/test_success.py".to_string(),
+            "tests/input/interrupt/test_interrupt.py".to_string(),
+            "tests/input/markers/test_markers.py".to_string(),
+            "tests/input/marks/test_class_marks.py".to_string(),
+            "tests/input/marks/test_module_skip.py".to_string(),
+            "tests/input/marks/test_pytestmark.py".to_string(),
             "tests/input/test_bad_file.py".to_string(),
             "tests/input/test_file.py".to_string(),
             "tests/input/test_fixtures.py".to_string(),
+            "tests/input/xunit/test_xunit.py".to_string(),
         ];
         expected.sort();
 
         assert_eq!(files, expected);
     }
 
+    #[test]
+    fn test_find_files_norecursedirs() {
+        let paths = vec!["tests/input".to_string()];
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let config = Config {
+            files: paths,
+            ignores: vec![],
+            norecursedirs: vec!["b?d".to_string(), "*ers".to_string()],
+            python_files,
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+        let _ = find_files(&config, tx);
+        let mut files: Vec<String> = rx.iter().collect();
+        files.sort();
+
+        let mut expected = vec![
+            "tests/input/asyncio/test_async.py".to_string(),
+            "tests/input/capture/test_capture.py".to_string(),
+            "tests/input/classes/test_classes.py".to_string(),
+            "tests/input/durations/test_durations.py".to_string(),
+            "tests/input/dynamic/test_conditional.py".to_string(),
+            "tests/input/folder/test_another_file.py".to_string(),
+            "tests/input/good/test_success.py".to_string(),
+            "tests/input/interrupt/test_interrupt.py".to_string(),
+            "tests/input/marks/test_class_marks.py".to_string(),
+            "tests/input/marks/test_module_skip.py".to_string(),
+            "tests/input/marks/test_pytestmark.py".to_string(),
+            "tests/input/test_bad_file.py".to_string(),
+            "tests/input/test_file.py".to_string(),
+            "tests/input/test_fixtures.py".to_string(),
+            "tests/input/xunit/test_xunit.py".to_string(),
+        ];
+        expected.sort();
+
+        assert_eq!(files, expected);
+    }
+
+    fn walk_fixture() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        for file in [
+            "test_root.py",
+            ".hidden/test_hidden.py",
+            "env/test_venv.py",
+            "env/pyvenv.cfg",
+            "generated/test_generated.py",
+            "skip_this/test_skipped.py",
+        ] {
+            let path = root.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        std::fs::write(root.path().join(".gitignore"), "generated/\n").unwrap();
+        root
+    }
+
+    fn find_in(root: &Path, config: Config) -> Vec<String> {
+        let python_files = Patterns::new(&["test_*.py".to_string()]).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let config = Config {
+            files: vec![root.to_string_lossy().to_string()],
+            python_files,
+            rootdir: root.to_path_buf(),
+            ..config
+        };
+        find_files(&config, tx).unwrap();
+        let mut files: Vec<String> = rx.iter().collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_find_files_prunes_directories() {
+        let root = walk_fixture();
+        let config = Config {
+            ignore_globs: vec![root.path().join("skip_*").to_string_lossy().to_string()],
+            norecursedirs: vec![".*".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            find_in(root.path(), config),
+            vec![
+                "generated/test_generated.py".to_string(),
+                "test_root.py".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_files_respect_gitignore() {
+        let root = walk_fixture();
+        let config = Config {
+            norecursedirs: vec![".*".to_string(), "skip_*".to_string()],
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            find_in(root.path(), config),
+            vec!["test_root.py".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_find_tests_keeps_walk_order() {
+        let files = vec![
+            "tests/input/xunit/test_xunit.py",
+            "tests/input/classes/test_classes.py",
+            "tests/input/good/test_success.py",
+            "tests/input/markers/test_markers.py",
+            "tests/input/folder/test_another_file.py",
+        ];
+        let config = Config {
+            collect_threads: 4,
+            python_classes: Patterns::new(&["Test".to_string()]).unwrap(),
+            python_functions: Patterns::new(&["test".to_string()]).unwrap(),
+            rootdir: env::current_dir().unwrap(),
+            ..Default::default()
+        };
+
+        let (tx_files, rx_files) = mpsc::channel();
+        for file in &files {
+            tx_files.send(file.to_string()).unwrap();
+        }
+        drop(tx_files);
+
+        let (tx, rx) = mpsc::channel();
+        find_tests(&config, rx_files, tx).unwrap();
+        let mut collected: Vec<String> = rx.iter().map(|test| test.file).collect();
+        collected.dedup();
+
+        assert_eq!(collected, files);
+    }
+
+    #[test]
+    fn test_find_tests_uses_cache() {
+        let root = tempfile::tempdir().unwrap();
+        let cache_dir = root.path().join(".rytest_cache");
+        std::fs::write(root.path().join("test_cached.py"), "def test_one(): pass").unwrap();
+        let config = Config {
+            cache_dir: Some(cache_dir.clone()),
+            python_functions: Patterns::new(&["test".to_string()]).unwrap(),
+            rootdir: root.path().to_path_buf(),
+            ..Default::default()
+        };
+        let collect = || {
+            let (tx_files, rx_files) = mpsc::channel();
+            tx_files.send("test_cached.py".to_string()).unwrap();
+            drop(tx_files);
+            let (tx, rx) = mpsc::channel();
+            find_tests(&config, rx_files, tx).unwrap();
+            rx.iter().map(|test| test.name).collect::<Vec<String>>()
+        };
+
+        assert_eq!(collect(), vec!["test_one".to_string()]);
+
+        // an unchanged file is taken from the cache without being parsed
+        let cache_file = cache_dir.join("collection.json");
+        let contents = std::fs::read_to_string(&cache_file).unwrap();
+        std::fs::write(&cache_file, contents.replace("test_one", "test_from_cache")).unwrap();
+        assert_eq!(collect(), vec!["test_from_cache".to_string()]);
+
+        std::fs::write(root.path().join("test_cached.py"), "def test_two(): pass").unwrap();
+        assert_eq!(collect(), vec!["test_two".to_string()]);
+    }
+
+    #[test]
+    fn test_find_tests_strict_markers() {
+        let collect = |markers: &[&str]| {
+            let config = Config {
+                markers: markers.iter().map(|marker| marker.to_string()).collect(),
+                python_classes: Patterns::new(&["Test".to_string()]).unwrap(),
+                python_functions: Patterns::new(&["test".to_string()]).unwrap(),
+                rootdir: env::current_dir().unwrap(),
+                strict_markers: true,
+                ..Default::default()
+            };
+            let (tx_files, rx_files) = mpsc::channel();
+            tx_files
+                .send("tests/input/markers/test_markers.py".to_string())
+                .unwrap();
+            drop(tx_files);
+            let (tx, rx) = mpsc::channel();
+            find_tests(&config, rx_files, tx).unwrap();
+            rx.iter()
+                .filter(|test| test.error.is_some())
+                .map(|test| test.name)
+                .collect::<Vec<String>>()
+        };
+
+        // registered markers are accepted, with or without a description
+        assert!(collect(&["slow: marks tests as slow", "db", "integration(reason)"]).is_empty());
+        assert_eq!(
+            collect(&["slow", "integration"]),
+            vec![
+                "test_slow_db",
+                "TestMarkedClass::test_in_class",
+                "TestMarkedClass::test_slow_in_class"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literal_parametrize_ids_match_python() {
+        let root = tempfile::tempdir().unwrap();
+        let source = indoc! {"
+            import pytest
+
+            @pytest.mark.parametrize('a', [1, -0, -3, 0.0, -0.0, 4.5, 0.0001, 1e15, 'x', None, True, False, ...])
+            def test_constants(a):
+                pass
+
+            @pytest.mark.parametrize('a', [int, sum, (1, -2), [None, 'y'], ((1, 2), 3)])
+            def test_names_and_sequences(a):
+                pass
+
+            @pytest.mark.parametrize('a', range(3, -4, -2))
+            def test_range(a):
+                pass
+
+            @pytest.mark.parametrize('a', [1, 2])
+            @pytest.mark.parametrize('b, c', [(-1, 'x'), (2.5, None)])
+            def test_stacked(a, b, c):
+                pass
+        "};
+        std::fs::write(root.path().join("test_ids.py"), source).unwrap();
+        let config = Config {
+            rootdir: root.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let ast = ast::Suite::parse(source, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        for stmt in &ast[1..] {
+            let mut test_markers = markers::get_markers(stmt, &symbols, source);
+            test_markers.reverse();
+            let outermost = parametrize::outermost_first(&test_markers);
+
+            let ids = parametrize::get_parametrizations(&outermost, &ast).unwrap();
+            let python_ids =
+                execution::get_parametrizations(&config, "test_ids.py", &outermost).unwrap();
+            assert_eq!(ids, python_ids);
+        }
+    }
+
     #[test]
     fn test_find_unittest_base() {
         let expr = Expr::Attribute(ast::ExprAttribute {
@@ -366,7 +1196,7 @@ mod tests {
             attr: Identifier::new("TestCase".to_string()),
             ctx: ast::ExprContext::Load,
         });
-        assert!(find_unittest_base(&expr));
+        assert!(find_unittest_base(&expr, &SymbolTable::default()));
     }
 
     #[test]
@@ -381,7 +1211,7 @@ mod tests {
             attr: Identifier::new("NotTestCase".to_string()),
             ctx: ast::ExprContext::Load,
         });
-        assert!(!find_unittest_base(&expr));
+        assert!(!find_unittest_base(&expr, &SymbolTable::default()));
     }
 
     #[test]
@@ -391,7 +1221,7 @@ mod tests {
             id: Identifier::new("unittest".to_string()),
             ctx: ast::ExprContext::Load,
         });
-        assert!(!find_unittest_base(&expr));
+        assert!(!find_unittest_base(&expr, &SymbolTable::default()));
     }
 
     #[test]
@@ -449,11 +1279,17 @@ mod tests {
                 type_comment: None,
             }),
         ];
-        let prefix = "test_";
+        let functions = Patterns::new(&["test_".to_string()]).unwrap();
         let file_name = "test.py".to_string();
         let verbose = false;
         assert_eq!(
-            find_unittest_class_cases(stmts, prefix, file_name, verbose),
+            find_unittest_class_cases(
+                stmts,
+                &functions,
+                &SymbolTable::default(),
+                file_name,
+                verbose
+            ),
             vec!["test_one".to_string(), "test_two".to_string()]
         );
     }
@@ -513,12 +1349,34 @@ mod tests {
                 type_comment: None,
             }),
         ];
-        let prefix = "test_";
+        let functions = Patterns::new(&["test_".to_string()]).unwrap();
         let file_name = "test.py".to_string();
         let verbose = false;
         assert_eq!(
-            find_unittest_class_cases(stmts, prefix, file_name, verbose),
+            find_unittest_class_cases(
+                stmts,
+                &functions,
+                &SymbolTable::default(),
+                file_name,
+                verbose
+            ),
             Vec::<String>::new()
         );
     }
+
+    #[test]
+    fn test_matches_node_id() {
+        let node_id = "tests/test_file.py::SomeTest::test_one[1-2]";
+        assert!(matches_node_id(node_id, node_id));
+        assert!(matches_node_id(node_id, "tests/test_file.py::SomeTest"));
+        assert!(matches_node_id(
+            node_id,
+            "tests/test_file.py::SomeTest::test_one"
+        ));
+        assert!(!matches_node_id(node_id, "tests/test_file.py::Some"));
+        assert!(!matches_node_id(
+            node_id,
+            "tests/test_file.py::SomeTest::test_one[1"
+        ));
+    }
 }
@@ -0,0 +1,105 @@
+use rustpython_parser::ast::Stmt;
+
+/// Returns the statements that run in the scope of `body`, walking into
+/// `if`, `try` and `with` blocks, so that tests defined under e.g.
+/// `if sys.version_info >= (3, 10):` are found. Every branch is included,
+/// since which one runs can't be known without running the module.
+pub fn statements(body: &[Stmt]) -> Vec<&Stmt> {
+    let mut statements = vec![];
+    for stmt in body {
+        match stmt {
+            Stmt::If(node) => {
+                statements.extend(self::statements(&node.body));
+                statements.extend(self::statements(&node.orelse));
+            }
+            Stmt::Try(node) => {
+                statements.extend(self::statements(&node.body));
+                for handler in &node.handlers {
+                    let handler = handler.as_except_handler().unwrap();
+                    statements.extend(self::statements(&handler.body));
+                }
+                statements.extend(self::statements(&node.orelse));
+                statements.extend(self::statements(&node.finalbody));
+            }
+            Stmt::TryStar(node) => {
+                statements.extend(self::statements(&node.body));
+                for handler in &node.handlers {
+                    let handler = handler.as_except_handler().unwrap();
+                    statements.extend(self::statements(&handler.body));
+                }
+                statements.extend(self::statements(&node.orelse));
+                statements.extend(self::statements(&node.finalbody));
+            }
+            Stmt::With(node) => statements.extend(self::statements(&node.body)),
+            Stmt::AsyncWith(node) => statements.extend(self::statements(&node.body)),
+            _ => statements.push(stmt),
+        }
+    }
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::indoc::indoc;
+    use rustpython_parser::{ast, Parse};
+
+    fn names(code: &str) -> Vec<String> {
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        statements(&ast)
+            .into_iter()
+            .filter_map(|stmt| stmt.as_function_def_stmt())
+            .map(|node| node.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_walks_into_conditional_blocks() {
+        let code = indoc! {"
+            import sys
+            def test_top():
+                pass
+            if sys.version_info >= (3, 10):
+                def test_if():
+                    pass
+            elif sys.platform == 'win32':
+                def test_elif():
+                    pass
+            else:
+                def test_else():
+                    pass
+            try:
+                import numpy
+                def test_try():
+                    pass
+            except ImportError:
+                def test_except():
+                    pass
+            with open(__file__):
+                def test_with():
+                    pass
+        "};
+        assert_eq!(
+            names(code),
+            vec![
+                "test_top",
+                "test_if",
+                "test_elif",
+                "test_else",
+                "test_try",
+                "test_except",
+                "test_with",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_leaves_function_bodies_alone() {
+        let code = indoc! {"
+            def make_tests():
+                def test_inner():
+                    pass
+        "};
+        assert_eq!(names(code), vec!["make_tests"]);
+    }
+}
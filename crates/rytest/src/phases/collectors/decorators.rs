@@ -0,0 +1,23 @@
+use rustpython_parser::ast;
+use rustpython_parser::ast::Stmt;
+
+use super::symbols::SymbolTable;
+
+/// Returns the decorators applied to a function or class.
+pub fn decorator_list(stmt: &Stmt) -> &[ast::Expr] {
+    match stmt {
+        Stmt::FunctionDef(node) => &node.decorator_list,
+        Stmt::AsyncFunctionDef(node) => &node.decorator_list,
+        Stmt::ClassDef(node) => &node.decorator_list,
+        _ => &[],
+    }
+}
+
+/// Checks whether a function or class is decorated with `name`, e.g.
+/// `pytest.mark.skip`, whether or not the decorator is called and however it
+/// was imported.
+pub fn has_decorator(stmt: &Stmt, symbols: &SymbolTable, name: &str) -> bool {
+    decorator_list(stmt)
+        .iter()
+        .any(|decorator| symbols.resolve(decorator).as_deref() == Some(name))
+}
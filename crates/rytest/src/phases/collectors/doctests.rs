@@ -0,0 +1,146 @@
+use rustpython_parser::ast::{self, Constant, Stmt};
+use std::path::Path;
+
+use super::blocks::statements;
+
+/// Finds the docstrings in a module that contain doctest examples, named the
+/// way Python's `doctest.DocTestFinder` names them, e.g. `pkg.mod.Class.method`,
+/// and sorted like it does.
+pub fn find_doctests(suite: &[Stmt], module: &str) -> Vec<String> {
+    let mut doctests = vec![];
+    if has_examples(suite) {
+        doctests.push(module.to_string());
+    }
+    find_in_body(suite, module, &mut doctests);
+    doctests.sort();
+    doctests
+}
+
+/// The dotted name a file is imported as, including the packages it's in.
+pub fn module_name(path: &Path) -> String {
+    let mut parts = vec![];
+    if let Some(stem) = path.file_stem() {
+        if stem != "__init__" {
+            parts.push(stem.to_string_lossy().to_string());
+        }
+    }
+
+    let mut dir = path.parent();
+    while let Some(package) = dir.filter(|dir| dir.join("__init__.py").is_file()) {
+        match package.file_name() {
+            Some(name) => parts.push(name.to_string_lossy().to_string()),
+            None => break,
+        }
+        dir = package.parent();
+    }
+
+    parts.reverse();
+    parts.join(".")
+}
+
+/// Whether text, such as a docstring or documentation file, has any examples.
+pub fn has_text_examples(text: &str) -> bool {
+    text.lines()
+        .any(|line| line.trim_start().starts_with(">>>"))
+}
+
+fn find_in_body(body: &[Stmt], prefix: &str, doctests: &mut Vec<String>) {
+    for stmt in statements(body) {
+        let (name, body) = match stmt {
+            Stmt::FunctionDef(node) => (node.name.as_str(), &node.body),
+            Stmt::AsyncFunctionDef(node) => (node.name.as_str(), &node.body),
+            Stmt::ClassDef(node) => (node.name.as_str(), &node.body),
+            _ => continue,
+        };
+        let name = format!("{}.{}", prefix, name);
+        if has_examples(body) {
+            doctests.push(name.clone());
+        }
+        // doctests are found in the methods of classes, but not in nested
+        // functions
+        if stmt.is_class_def_stmt() {
+            find_in_body(body, &name, doctests);
+        }
+    }
+}
+
+fn has_examples(body: &[Stmt]) -> bool {
+    match body.first() {
+        Some(Stmt::Expr(node)) => match node.value.as_ref() {
+            ast::Expr::Constant(ast::ExprConstant {
+                value: Constant::Str(docstring),
+                ..
+            }) => has_text_examples(docstring),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::indoc::indoc;
+    use rustpython_parser::Parse;
+
+    #[test]
+    fn it_finds_docstrings_with_examples() {
+        let code = indoc! {r#"
+            """
+            >>> 1 + 1
+            2
+            """
+
+            def add(a, b):
+                """
+                >>> add(1, 2)
+                3
+                """
+                return a + b
+
+            def undocumented():
+                """No examples here."""
+
+            class Calculator:
+                """
+                >>> Calculator().value
+                0
+                """
+                value = 0
+
+                def double(self):
+                    """
+                    >>> Calculator().double()
+                    0
+                    """
+                    def nested():
+                        """
+                        >>> nested()
+                        """
+                    return self.value * 2
+        "#};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        assert_eq!(
+            find_doctests(&ast, "calc"),
+            vec![
+                "calc".to_string(),
+                "calc.Calculator".to_string(),
+                "calc.Calculator.double".to_string(),
+                "calc.add".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_names_modules_after_their_packages() {
+        let root = tempfile::tempdir().unwrap();
+        let package = root.path().join("pkg").join("sub");
+        std::fs::create_dir_all(&package).unwrap();
+        std::fs::write(root.path().join("pkg").join("__init__.py"), "").unwrap();
+        std::fs::write(package.join("__init__.py"), "").unwrap();
+
+        assert_eq!(module_name(&package.join("mod.py")), "pkg.sub.mod");
+        assert_eq!(module_name(&package.join("__init__.py")), "pkg.sub");
+        assert_eq!(module_name(&root.path().join("top.py")), "top");
+    }
+}
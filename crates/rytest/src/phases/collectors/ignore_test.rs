@@ -1,35 +1,10 @@
-use rustpython_parser::ast;
 use rustpython_parser::ast::Stmt;
-use rustpython_parser::ast::Stmt::FunctionDef;
 
-pub fn is_pytest_fixture(stmt: &Stmt) -> bool {
-    match stmt {
-        FunctionDef(node) => node.decorator_list.iter().any(|decorator| {
-            match decorator {
-                // handle pytest.fixture decorator called with parameters
-                ast::Expr::Call(call) => match call.func.as_attribute_expr() {
-                    Some(attr_expr) => match attr_expr.value.as_name_expr() {
-                        Some(name_expr) => {
-                            let module = name_expr.id.as_str();
-                            module == "pytest" && attr_expr.attr.as_str() == "fixture"
-                        }
-                        None => false,
-                    },
-                    None => false,
-                },
-                // handle pytest.fixture decorator called without parameters
-                ast::Expr::Attribute(attr_expr) => match attr_expr.value.as_name_expr() {
-                    Some(name_expr) => {
-                        let module = name_expr.id.as_str();
-                        module == "pytest" && attr_expr.attr.as_str() == "fixture"
-                    }
-                    None => false,
-                },
-                _ => false,
-            }
-        }),
-        _ => false,
-    }
+use super::decorators::has_decorator;
+use super::symbols::SymbolTable;
+
+pub fn is_pytest_fixture(stmt: &Stmt, symbols: &SymbolTable) -> bool {
+    has_decorator(stmt, symbols, "pytest.fixture")
 }
 
 #[cfg(test)]
@@ -44,8 +19,9 @@ mod tests {
         def test_not_a_fixture():
             pass
         ";
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_pytest_fixture(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_pytest_fixture(ast.last().unwrap(), &symbols);
         assert!(!result);
     }
 
@@ -56,8 +32,9 @@ mod tests {
             def test_fixture():
                 pass
         "};
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_pytest_fixture(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_pytest_fixture(ast.last().unwrap(), &symbols);
         assert!(result);
     }
 
@@ -68,8 +45,37 @@ mod tests {
             def test_fixture():
                 pass
         "};
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_pytest_fixture(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_pytest_fixture(ast.last().unwrap(), &symbols);
+        assert!(result);
+    }
+
+    #[test]
+    fn it_works_with_fixture_imported_from_pytest() {
+        let code = indoc! {"
+            from pytest import fixture
+            @fixture
+            def test_fixture():
+                pass
+        "};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_pytest_fixture(ast.last().unwrap(), &symbols);
         assert!(result);
     }
+
+    #[test]
+    fn it_ignores_unrelated_fixture_decorators() {
+        let code = indoc! {"
+            from mylib import fixture
+            @fixture
+            def test_fixture():
+                pass
+        "};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_pytest_fixture(ast.last().unwrap(), &symbols);
+        assert!(!result);
+    }
 }
@@ -0,0 +1,170 @@
+use rustpython_parser::ast::{self, Ranged, Stmt};
+
+use super::blocks::statements;
+use super::decorators::decorator_list;
+use super::symbols::SymbolTable;
+use crate::Marker;
+
+/// Markers pytest always knows about, even with `--strict-markers`.
+pub const BUILTIN_MARKERS: [&str; 6] = [
+    "filterwarnings",
+    "parametrize",
+    "skip",
+    "skipif",
+    "usefixtures",
+    "xfail",
+];
+
+/// Extracts every `@pytest.mark.<name>` decorator applied to a function or
+/// class, keeping the source of its arguments so they can be evaluated later.
+pub fn get_markers(stmt: &Stmt, symbols: &SymbolTable, source: &str) -> Vec<Marker> {
+    decorator_list(stmt)
+        .iter()
+        .filter_map(|decorator| to_marker(decorator, symbols, source))
+        .collect()
+}
+
+/// Extracts the markers assigned to `pytestmark` in a module or class body,
+/// either as a single marker or a list of them.
+pub fn get_pytestmark(body: &[Stmt], symbols: &SymbolTable, source: &str) -> Vec<Marker> {
+    let mut markers = vec![];
+    for stmt in statements(body) {
+        if let Stmt::Assign(node) = stmt {
+            let is_pytestmark = node.targets.iter().any(|target| {
+                target
+                    .as_name_expr()
+                    .is_some_and(|name| name.id.as_str() == "pytestmark")
+            });
+            if !is_pytestmark {
+                continue;
+            }
+            markers = match node.value.as_ref() {
+                ast::Expr::List(list) => list
+                    .elts
+                    .iter()
+                    .filter_map(|elt| to_marker(elt, symbols, source))
+                    .collect(),
+                ast::Expr::Tuple(tuple) => tuple
+                    .elts
+                    .iter()
+                    .filter_map(|elt| to_marker(elt, symbols, source))
+                    .collect(),
+                value => to_marker(value, symbols, source).into_iter().collect(),
+            };
+        }
+    }
+    markers
+}
+
+/// Returns the name portion of a marker registered in configuration, e.g.
+/// `slow` for `slow: marks tests as slow`.
+pub fn registered_name(marker: &str) -> &str {
+    marker.split([':', '(']).next().unwrap_or(marker).trim()
+}
+
+fn to_marker(expr: &ast::Expr, symbols: &SymbolTable, source: &str) -> Option<Marker> {
+    let qualified_name = symbols.resolve(expr)?;
+    let name = qualified_name.strip_prefix("pytest.mark.")?;
+    if name.contains('.') {
+        return None;
+    }
+
+    let (args, kwargs) = match expr {
+        ast::Expr::Call(call) => (
+            call.args
+                .iter()
+                .map(|arg| source[arg.range()].to_string())
+                .collect(),
+            call.keywords
+                .iter()
+                .filter_map(|keyword| {
+                    keyword
+                        .arg
+                        .as_ref()
+                        .map(|arg| (arg.to_string(), source[keyword.value.range()].to_string()))
+                })
+                .collect(),
+        ),
+        _ => (vec![], vec![]),
+    };
+
+    Some(Marker {
+        name: name.to_string(),
+        args,
+        kwargs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::indoc::indoc;
+    use rustpython_parser::{ast, Parse};
+
+    #[test]
+    fn it_extracts_decorator_markers() {
+        let code = indoc! {"
+            from pytest import mark
+            @mark.slow
+            @mark.skipif(sys.platform == 'win32', reason='no windows')
+            @other.decorator
+            def test_marked():
+                pass
+        "};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let markers = get_markers(ast.last().unwrap(), &symbols, code);
+        assert_eq!(
+            markers,
+            vec![
+                Marker {
+                    name: "slow".to_string(),
+                    args: vec![],
+                    kwargs: vec![],
+                },
+                Marker {
+                    name: "skipif".to_string(),
+                    args: vec!["sys.platform == 'win32'".to_string()],
+                    kwargs: vec![("reason".to_string(), "'no windows'".to_string())],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_extracts_pytestmark_lists() {
+        let code = indoc! {"
+            import pytest
+            pytestmark = [pytest.mark.slow, pytest.mark.db]
+        "};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let names: Vec<String> = get_pytestmark(&ast, &symbols, code)
+            .into_iter()
+            .map(|marker| marker.name)
+            .collect();
+        assert_eq!(names, vec!["slow".to_string(), "db".to_string()]);
+    }
+
+    #[test]
+    fn it_extracts_single_pytestmark() {
+        let code = indoc! {"
+            import pytest
+            pytestmark = pytest.mark.slow
+        "};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let names: Vec<String> = get_pytestmark(&ast, &symbols, code)
+            .into_iter()
+            .map(|marker| marker.name)
+            .collect();
+        assert_eq!(names, vec!["slow".to_string()]);
+    }
+
+    #[test]
+    fn it_parses_registered_names() {
+        assert_eq!(registered_name("slow: marks tests as slow"), "slow");
+        assert_eq!(registered_name("env(name): run on env"), "env");
+        assert_eq!(registered_name("db"), "db");
+    }
+}
@@ -1,3 +1,7 @@
-pub mod ignore_skip;
+pub mod blocks;
+pub mod decorators;
+pub mod doctests;
 pub mod ignore_test;
+pub mod markers;
 pub mod parametrize;
+pub mod symbols;
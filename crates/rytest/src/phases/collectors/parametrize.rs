@@ -1,38 +1,214 @@
-use rustpython_parser::ast;
-use rustpython_parser::ast::Stmt;
-use rustpython_parser::ast::Stmt::FunctionDef;
-
-pub fn is_parametrized(stmt: &Stmt) -> bool {
-    match stmt {
-        FunctionDef(node) => {
-            for decorator in &node.decorator_list {
-                if let ast::Expr::Call(call) = decorator {
-                    if let Some(attr_expr) = call.func.as_attribute_expr() {
-                        if let Some(nested_attr_expr) = attr_expr.value.as_attribute_expr() {
-                            if let Some(name_expr) = nested_attr_expr.value.as_name_expr() {
-                                let module = name_expr.id.as_str();
-                                if module == "pytest"
-                                    && nested_attr_expr.attr.as_str() == "mark"
-                                    && attr_expr.attr.as_str() == "parametrize"
-                                {
-                                    return true;
-                                }
-                            }
-                        }
-                    }
-                }
+use rustpython_parser::ast::{self, Constant, Stmt, UnaryOp};
+use rustpython_parser::Parse;
+
+use crate::Marker;
+
+/// Builtins whose parameter ID is their `__name__`, as long as the module
+/// doesn't rebind them.
+const NAMED_BUILTINS: [&str; 16] = [
+    "abs", "all", "any", "bool", "bytes", "dict", "float", "int", "len", "list", "max", "min",
+    "round", "set", "str", "sum",
+];
+
+pub fn is_parametrized(markers: &[Marker]) -> bool {
+    markers.iter().any(|marker| marker.name == "parametrize")
+}
+
+/// A test's `parametrize` markers, outermost first, from its markers listed
+/// closest first.
+pub fn outermost_first(markers: &[Marker]) -> Vec<&Marker> {
+    markers
+        .iter()
+        .rev()
+        .filter(|marker| marker.name == "parametrize")
+        .collect()
+}
+
+/// Computes the parameter IDs of a test from its `parametrize` markers,
+/// outermost first. Returns `None` if any `argvalues` aren't literals or
+/// `range()` calls, or the markers have options such as `ids`, so the module
+/// has to be run to find them.
+pub fn get_parametrizations(markers: &[&Marker], suite: &[Stmt]) -> Option<Vec<String>> {
+    let mut ids: Vec<String> = vec![];
+
+    for marker in markers {
+        if marker.kwargs.iter().any(|(name, _)| name != "argvalues") {
+            return None;
+        }
+        let argvalues = ast::Expr::parse(argvalues(marker)?, "<embedded>").ok()?;
+        ids = combine(ids, parameter_ids(&argvalues, suite)?);
+    }
+
+    Some(ids)
+}
+
+/// The source of a `parametrize` marker's `argvalues`.
+pub fn argvalues(marker: &Marker) -> Option<&str> {
+    match &marker.args[..] {
+        [_, argvalues, ..] => Some(argvalues),
+        _ => marker
+            .kwargs
+            .iter()
+            .find(|(name, _)| name == "argvalues")
+            .map(|(_, argvalues)| argvalues.as_str()),
+    }
+}
+
+/// Combines the IDs of an outer `parametrize` with those of the next one in,
+/// as `outer-inner`.
+pub fn combine(ids: Vec<String>, parameters: Vec<String>) -> Vec<String> {
+    if ids.is_empty() {
+        return parameters;
+    }
+
+    ids.iter()
+        .flat_map(|id| {
+            parameters
+                .iter()
+                .map(move |parameter| format!("{}-{}", id, parameter))
+        })
+        .collect()
+}
+
+/// The IDs of each value in `argvalues`, expanding `range()` calls.
+fn parameter_ids(argvalues: &ast::Expr, suite: &[Stmt]) -> Option<Vec<String>> {
+    match argvalues {
+        ast::Expr::List(ast::ExprList { elts, .. })
+        | ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => elts
+            .iter()
+            .map(|value| parameter_id(value, suite))
+            .collect(),
+        ast::Expr::Call(call)
+            if call
+                .func
+                .as_name_expr()
+                .is_some_and(|f| f.id.as_str() == "range")
+                && call.keywords.is_empty() =>
+        {
+            let args = call
+                .args
+                .iter()
+                .map(int_value)
+                .collect::<Option<Vec<i64>>>()?;
+            let (start, stop, step) = match args[..] {
+                [stop] => (0, stop, 1),
+                [start, stop] => (start, stop, 1),
+                [start, stop, step] if step != 0 => (start, stop, step),
+                _ => return None,
+            };
+
+            let mut ids = vec![];
+            let mut i = start;
+            while (step > 0 && i < stop) || (step < 0 && i > stop) {
+                ids.push(i.to_string());
+                i = i.checked_add(step)?;
             }
-            false
+            Some(ids)
         }
-        _ => false,
+        _ => None,
+    }
+}
+
+fn int_value(expr: &ast::Expr) -> Option<i64> {
+    match expr {
+        ast::Expr::Constant(constant) => match &constant.value {
+            Constant::Int(i) => i.to_string().parse().ok(),
+            _ => None,
+        },
+        ast::Expr::UnaryOp(op) if op.op == UnaryOp::USub => int_value(&op.operand).map(|i| -i),
+        _ => None,
+    }
+}
+
+/// The ID of a single parameter: `str()` of it, or its `__name__`, with
+/// tuples and lists joined by `-`.
+fn parameter_id(value: &ast::Expr, suite: &[Stmt]) -> Option<String> {
+    match value {
+        ast::Expr::Constant(constant) => constant_id(&constant.value),
+        // negated rather than prefixed with `-`, as `str(-0)` is `0`
+        ast::Expr::UnaryOp(op) if op.op == UnaryOp::USub => match op.operand.as_ref() {
+            ast::Expr::Constant(ast::ExprConstant {
+                value: Constant::Int(i),
+                ..
+            }) => Some((-i).to_string()),
+            ast::Expr::Constant(ast::ExprConstant {
+                value: Constant::Float(f),
+                ..
+            }) => constant_id(&Constant::Float(-f)),
+            _ => None,
+        },
+        ast::Expr::List(ast::ExprList { elts, .. })
+        | ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => Some(
+            elts.iter()
+                .map(|elt| parameter_id(elt, suite))
+                .collect::<Option<Vec<String>>>()?
+                .join("-"),
+        ),
+        ast::Expr::Name(name)
+            if NAMED_BUILTINS.contains(&name.id.as_str()) && !is_bound(suite, name.id.as_str()) =>
+        {
+            Some(name.id.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn constant_id(constant: &Constant) -> Option<String> {
+    match constant {
+        Constant::None => Some("None".to_string()),
+        Constant::Bool(true) => Some("True".to_string()),
+        Constant::Bool(false) => Some("False".to_string()),
+        Constant::Str(s) => Some(s.to_string()),
+        Constant::Int(i) => Some(i.to_string()),
+        // Rust's shortest float representation matches Python's `repr` until
+        // Python switches to scientific notation
+        Constant::Float(f) if *f == 0.0 || (1e-4..1e16).contains(&f.abs()) => {
+            Some(format!("{:?}", f))
+        }
+        Constant::Tuple(items) => Some(
+            items
+                .iter()
+                .map(constant_id)
+                .collect::<Option<Vec<String>>>()?
+                .join("-"),
+        ),
+        Constant::Ellipsis => Some("Ellipsis".to_string()),
+        _ => None,
     }
 }
 
+/// Checks whether a name might be bound at the top level of a module.
+fn is_bound(suite: &[Stmt], name: &str) -> bool {
+    let binds = |target: &ast::Expr| target.as_name_expr().is_some_and(|t| t.id.as_str() == name);
+
+    suite.iter().any(|stmt| match stmt {
+        Stmt::FunctionDef(node) => node.name.as_str() == name,
+        Stmt::AsyncFunctionDef(node) => node.name.as_str() == name,
+        Stmt::ClassDef(node) => node.name.as_str() == name,
+        Stmt::Assign(node) => node.targets.iter().any(binds),
+        Stmt::AnnAssign(node) => binds(&node.target),
+        Stmt::AugAssign(node) => binds(&node.target),
+        Stmt::Import(node) => node.names.iter().any(|alias| {
+            let bound = alias.asname.as_ref().unwrap_or(&alias.name);
+            bound.as_str().split('.').next() == Some(name)
+        }),
+        Stmt::ImportFrom(node) => node.names.iter().any(|alias| {
+            alias.name.as_str() == "*"
+                || alias.asname.as_ref().unwrap_or(&alias.name).as_str() == name
+        }),
+        // anything fancier could rebind it too
+        Stmt::For(_) | Stmt::With(_) | Stmt::If(_) | Stmt::Try(_) => true,
+        _ => false,
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use super::super::markers::get_markers;
+    use super::super::symbols::SymbolTable;
     use super::*;
     use pyo3::indoc::indoc;
-    use rustpython_parser::{ast, Parse};
+    use rustpython_parser::ast;
 
     #[test]
     fn it_works_with_non_parameterized_test() {
@@ -40,8 +216,9 @@ mod tests {
             def test_not_parameterized():
                 pass
         "};
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_parametrized(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_parametrized(&get_markers(ast.last().unwrap(), &symbols, code));
         assert!(!result);
     }
 
@@ -52,8 +229,9 @@ mod tests {
             def test_parameterized():
                 pass
         "};
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_parametrized(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_parametrized(&get_markers(ast.last().unwrap(), &symbols, code));
         assert!(result);
     }
 
@@ -64,8 +242,9 @@ mod tests {
             def test_parameterized():
                 pass
         "};
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_parametrized(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_parametrized(&get_markers(ast.last().unwrap(), &symbols, code));
         assert!(result);
     }
 
@@ -76,8 +255,9 @@ mod tests {
             def test_parameterized():
                 pass
         "};
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_parametrized(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_parametrized(&get_markers(ast.last().unwrap(), &symbols, code));
         assert!(result);
     }
 
@@ -88,8 +268,165 @@ mod tests {
             def test_parameterized(a):
                 pass
         "};
-        let ast = ast::Suite::parse(code, "<embedded>");
-        let result = is_parametrized(ast.unwrap().first().take().unwrap());
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_parametrized(&get_markers(ast.last().unwrap(), &symbols, code));
+        assert!(result);
+    }
+
+    fn parametrizations(code: &str) -> Option<Vec<String>> {
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let mut markers = get_markers(ast.last().unwrap(), &symbols, code);
+        markers.reverse();
+        get_parametrizations(&outermost_first(&markers), &ast)
+    }
+
+    #[test]
+    fn it_computes_literal_ids() {
+        let code = indoc! {"
+            @pytest.mark.parametrize('a, b', [(1, 2), (-3, 4.5), ('x', None), (True, int)])
+            def test_parameterized(a, b):
+                pass
+        "};
+        assert_eq!(
+            parametrizations(code),
+            Some(vec![
+                "1-2".to_string(),
+                "-3-4.5".to_string(),
+                "x-None".to_string(),
+                "True-int".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn it_computes_range_ids() {
+        let code = indoc! {"
+            @pytest.mark.parametrize('a', range(1, 6, 2))
+            def test_parameterized(a):
+                pass
+        "};
+        assert_eq!(
+            parametrizations(code),
+            Some(vec!["1".to_string(), "3".to_string(), "5".to_string()])
+        );
+    }
+
+    #[test]
+    fn it_negates_values() {
+        let code = indoc! {"
+            @pytest.mark.parametrize('a', [-0, -0.0, -1, -2.5])
+            def test_parameterized(a):
+                pass
+        "};
+        assert_eq!(
+            parametrizations(code),
+            Some(vec![
+                "0".to_string(),
+                "-0.0".to_string(),
+                "-1".to_string(),
+                "-2.5".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn it_leaves_overflowing_ranges_to_python() {
+        let code = indoc! {"
+            @pytest.mark.parametrize('a', range(9223372036854775800, 9223372036854775807, 5))
+            def test_parameterized(a):
+                pass
+        "};
+        assert_eq!(parametrizations(code), None);
+    }
+
+    #[test]
+    fn it_combines_stacked_decorators() {
+        let code = indoc! {"
+            @pytest.mark.parametrize('c', ['a', 'c'])
+            @pytest.mark.parametrize('a,b', [(1, 2), (3, 4)])
+            def test_parameterized(a, b, c):
+                pass
+        "};
+        assert_eq!(
+            parametrizations(code),
+            Some(vec![
+                "a-1-2".to_string(),
+                "a-3-4".to_string(),
+                "c-1-2".to_string(),
+                "c-3-4".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn it_orders_class_markers_outside_method_markers() {
+        let code = indoc! {"
+            @pytest.mark.parametrize('a', [1, 2])
+            class TestParameterized:
+                @pytest.mark.parametrize('b', [3])
+                def test_parameterized(self, a, b):
+                    pass
+        "};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let class = ast.last().unwrap();
+        let method = &class.as_class_def_stmt().unwrap().body[0];
+        let mut markers = get_markers(class, &symbols, code);
+        markers.extend(get_markers(method, &symbols, code));
+        markers.reverse();
+
+        assert_eq!(
+            get_parametrizations(&outermost_first(&markers), &ast),
+            Some(vec!["1-3".to_string(), "2-3".to_string()])
+        );
+    }
+
+    #[test]
+    fn it_leaves_dynamic_values_to_python() {
+        let comprehension = indoc! {"
+            @pytest.mark.parametrize('a', [x for x in range(3)])
+            def test_parameterized(a):
+                pass
+        "};
+        assert_eq!(parametrizations(comprehension), None);
+
+        let variable = indoc! {"
+            VALUES = [1, 2]
+            @pytest.mark.parametrize('a', VALUES)
+            def test_parameterized(a):
+                pass
+        "};
+        assert_eq!(parametrizations(variable), None);
+
+        let rebound = indoc! {"
+            from helpers import sum
+            @pytest.mark.parametrize('a', [sum])
+            def test_parameterized(a):
+                pass
+        "};
+        assert_eq!(parametrizations(rebound), None);
+
+        let ids = indoc! {"
+            @pytest.mark.parametrize('a', [1, 2], ids=['one', 'two'])
+            def test_parameterized(a):
+                pass
+        "};
+        assert_eq!(parametrizations(ids), None);
+    }
+
+    #[test]
+    fn it_works_with_mark_aliased() {
+        let code = indoc! {"
+            from pytest import mark as m
+            @m.parametrize('a', [1, 2, 3])
+            def test_parameterized(a):
+                pass
+        "};
+        let ast = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&ast);
+        let result = is_parametrized(&get_markers(ast.last().unwrap(), &symbols, code));
         assert!(result);
     }
 }
@@ -0,0 +1,145 @@
+use rustpython_parser::ast;
+use rustpython_parser::ast::Stmt;
+use std::collections::HashMap;
+
+use super::blocks::statements;
+
+/// Maps names bound at the top level of a file, including inside `if` and
+/// `try` blocks, to the qualified names they refer to, so that
+/// `from pytest import mark` or `import pytest as pt` can be resolved back to
+/// `pytest.mark`.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    aliases: HashMap<String, String>,
+}
+
+impl SymbolTable {
+    pub fn from_suite(suite: &[Stmt]) -> SymbolTable {
+        let mut symbols = SymbolTable::default();
+
+        for stmt in statements(suite) {
+            match stmt {
+                Stmt::Import(node) => {
+                    for alias in &node.names {
+                        let name = alias.name.as_str();
+                        match &alias.asname {
+                            Some(asname) => symbols.bind(asname.as_str(), name.to_string()),
+                            // `import a.b` only binds `a`
+                            None => {
+                                let root = name.split('.').next().unwrap_or(name);
+                                symbols.bind(root, root.to_string())
+                            }
+                        }
+                    }
+                }
+                Stmt::ImportFrom(node) if node.level.map_or(0, |l| l.to_u32()) == 0 => {
+                    if let Some(module) = &node.module {
+                        for alias in &node.names {
+                            let local = alias.asname.as_ref().unwrap_or(&alias.name);
+                            symbols.bind(
+                                local.as_str(),
+                                format!("{}.{}", module.as_str(), alias.name.as_str()),
+                            );
+                        }
+                    }
+                }
+                // handle aliases such as `mark = pytest.mark`
+                Stmt::Assign(node) if node.targets.len() == 1 => {
+                    if let (Some(target), Some(value)) =
+                        (node.targets[0].as_name_expr(), symbols.resolve(&node.value))
+                    {
+                        symbols.bind(target.id.as_str(), value);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        symbols
+    }
+
+    fn bind(&mut self, name: &str, qualified_name: String) {
+        self.aliases.insert(name.to_string(), qualified_name);
+    }
+
+    /// Resolves a name or attribute chain to its qualified name, looking
+    /// through calls so `pytest.mark.skip(reason="...")` resolves to
+    /// `pytest.mark.skip`. Names that were never bound resolve to themselves.
+    pub fn resolve(&self, expr: &ast::Expr) -> Option<String> {
+        match expr {
+            ast::Expr::Name(name) => Some(
+                self.aliases
+                    .get(name.id.as_str())
+                    .cloned()
+                    .unwrap_or_else(|| name.id.to_string()),
+            ),
+            ast::Expr::Attribute(attr) => self
+                .resolve(&attr.value)
+                .map(|value| format!("{}.{}", value, attr.attr.as_str())),
+            ast::Expr::Call(call) => self.resolve(&call.func),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::indoc::indoc;
+    use rustpython_parser::{ast, Parse};
+
+    fn resolve_last(code: &str) -> Option<String> {
+        let suite = ast::Suite::parse(code, "<embedded>").unwrap();
+        let symbols = SymbolTable::from_suite(&suite);
+        let expr = suite.last().unwrap().as_expr_stmt().unwrap();
+        symbols.resolve(&expr.value)
+    }
+
+    #[test]
+    fn it_resolves_unbound_names_to_themselves() {
+        assert_eq!(
+            resolve_last("pytest.mark.skip"),
+            Some("pytest.mark.skip".to_string())
+        );
+    }
+
+    #[test]
+    fn it_resolves_module_aliases() {
+        let code = indoc! {"
+            import pytest as pt
+            pt.mark.skip
+        "};
+        assert_eq!(resolve_last(code), Some("pytest.mark.skip".to_string()));
+    }
+
+    #[test]
+    fn it_resolves_from_imports() {
+        let code = indoc! {"
+            from pytest import mark as m
+            m.parametrize('a', [1])
+        "};
+        assert_eq!(
+            resolve_last(code),
+            Some("pytest.mark.parametrize".to_string())
+        );
+    }
+
+    #[test]
+    fn it_resolves_assignments() {
+        let code = indoc! {"
+            import pytest as pt
+            mark = pt.mark
+            mark.skip
+        "};
+        assert_eq!(resolve_last(code), Some("pytest.mark.skip".to_string()));
+    }
+
+    #[test]
+    fn it_ignores_relative_imports() {
+        let code = indoc! {"
+            from .pytest import fixture
+            fixture
+        "};
+        assert_eq!(resolve_last(code), Some("fixture".to_string()));
+    }
+}
@@ -1,176 +1,1158 @@
 use anyhow::Result;
+use pyo3::exceptions::PyKeyboardInterrupt;
 use pyo3::prelude::*;
-use pyo3::types::{PyIterator, PyMapping, PyString};
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{PyDict, PyIterator, PyMapping, PyString, PyType};
 use pyo3::{indoc::indoc, types::PyTuple};
-use std::{env, fs, path::Path, sync::mpsc};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::{fs, mem, sync::mpsc, time::Instant};
 
+use super::collectors::{doctests, parametrize};
 use crate::python;
-use crate::TestCase;
+use crate::{Config, Durations, Event, Marker, TestCase};
+
+/// Runs coroutines on an event loop kept open until `close`, one for each
+/// fixture scope, so async fixtures share a loop with everything they outlive.
+/// trio has no loop to keep open, so each coroutine gets its own `trio.run`.
+/// Coroutines created but never awaited fail the test rather than passing
+/// silently.
+const ASYNC_SUPPORT: &str = indoc! {"
+import asyncio
+import inspect
+import warnings
+
+
+class Runner:
+    def __init__(self, backend):
+        self.backend = backend
+        self.loop = None
+
+    def run(self, awaitable):
+        if self.backend == 'trio':
+            import trio
+
+            async def main():
+                return await awaitable
+
+            return trio.run(main)
+
+        if self.loop is None:
+            if self.backend == 'uvloop':
+                import uvloop
+                self.loop = uvloop.new_event_loop()
+            else:
+                self.loop = asyncio.new_event_loop()
+        return self.loop.run_until_complete(awaitable)
+
+    def call(self, function, args):
+        with warnings.catch_warnings(record=True) as caught:
+            warnings.filterwarnings(
+                'always', message='coroutine .* was never awaited', category=RuntimeWarning
+            )
+            result = function(*args)
+            if inspect.isawaitable(result):
+                result = self.run(result)
+
+        never_awaited = None
+        for warning in caught:
+            if issubclass(warning.category, RuntimeWarning) and 'was never awaited' in str(
+                warning.message
+            ):
+                never_awaited = warning.message
+            else:
+                warnings.showwarning(
+                    warning.message, warning.category, warning.filename, warning.lineno
+                )
+        if never_awaited is not None:
+            raise RuntimeError(str(never_awaited))
+        return result
+
+    def close(self):
+        if self.loop is not None:
+            self.loop.run_until_complete(self.loop.shutdown_asyncgens())
+            self.loop.close()
+            self.loop = None
+"};
+
+/// The module a test lives in, kept alive between tests so that xunit-style
+/// `setup_module`/`teardown_module` hooks wrap every test in the file.
+struct ModuleScope {
+    file: String,
+    module: PyResult<Py<PyModule>>,
+    class: Option<ClassScope>,
+    fixtures: FixtureScope,
+}
+
+/// The class a test lives in, kept alive so that `setup_class`/`teardown_class`
+/// (or `setUpClass`/`tearDownClass` for unittest) wrap every test in the class.
+struct ClassScope {
+    name: String,
+    class: PyResult<Py<PyAny>>,
+    unittest: bool,
+    fixtures: FixtureScope,
+}
+
+/// The fixtures set up in a scope, which last until the scope is torn down,
+/// along with the event loop their coroutines run on.
+#[derive(Default)]
+struct FixtureScope {
+    runner: Option<Py<PyAny>>,
+    values: HashMap<String, PyObject>,
+    /// Generator fixtures to finish when the scope is torn down.
+    generators: Vec<Py<PyAny>>,
+}
+
+impl FixtureScope {
+    /// The scope's runner, created the first time it's needed.
+    fn runner<'py>(&mut self, py: Python<'py>, backend: &str) -> PyResult<Bound<'py, PyAny>> {
+        static SUPPORT: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+
+        if self.runner.is_none() {
+            let support = SUPPORT.get_or_try_init(py, || {
+                Ok::<_, PyErr>(PyModule::from_code_bound(py, ASYNC_SUPPORT, "", "")?.unbind())
+            })?;
+            let runner = support.bind(py).getattr("Runner")?.call1((backend,))?;
+            self.runner = Some(runner.unbind());
+        }
+        Ok(self.runner.as_ref().unwrap().bind(py).clone())
+    }
+
+    /// Runs generator fixtures to completion, most recent first, then closes
+    /// the scope's event loop.
+    fn finish(self, py: Python) -> PyResult<()> {
+        let isasyncgen = py.import_bound("inspect")?.getattr("isasyncgen")?;
+        for generator in self.generators.into_iter().rev() {
+            match &self.runner {
+                Some(runner) if isasyncgen.call1((&generator,))?.is_truthy()? => {
+                    while let Ok(_next_item) =
+                        runner.call_method1(py, "run", (generator.call_method0(py, "__anext__")?,))
+                    {
+                        // just eat the result
+                    }
+                }
+                _ => {
+                    while let Ok(_next_item) = generator.getattr(py, "__next__")?.call0(py) {
+                        // just eat the result
+                    }
+                }
+            }
+        }
+        if let Some(runner) = self.runner {
+            runner.call_method0(py, "close")?;
+        }
+        Ok(())
+    }
+}
+
+/// The fixture scopes around a test, which fixtures are kept in according to
+/// their `scope`.
+struct Scopes<'a> {
+    function: FixtureScope,
+    /// Missing for tests outside a class, whose class fixtures are set up
+    /// for each test as in pytest.
+    class: Option<&'a mut FixtureScope>,
+    module: &'a mut FixtureScope,
+    session: &'a mut FixtureScope,
+}
+
+impl Scopes<'_> {
+    fn get(&mut self, scope: &str) -> &mut FixtureScope {
+        match scope {
+            "class" => match self.class {
+                Some(ref mut class) => class,
+                None => &mut self.function,
+            },
+            "module" => self.module,
+            // packages aren't collected, so their fixtures last the session
+            "package" | "session" => self.session,
+            _ => &mut self.function,
+        }
+    }
+}
+
+/// Redirects `sys.stdout` and `sys.stderr` to buffers while a test runs, so
+/// what it prints can be reported along with its result.
+struct Capture {
+    saved: (Py<PyAny>, Py<PyAny>),
+    buffers: (Py<PyAny>, Py<PyAny>),
+}
+
+impl Capture {
+    fn start(py: Python) -> PyResult<Capture> {
+        let sys = py.import_bound("sys")?;
+        let io = py.import_bound("io")?;
+        let saved = (sys.getattr("stdout")?, sys.getattr("stderr")?);
+        let buffers = (io.call_method0("StringIO")?, io.call_method0("StringIO")?);
+        sys.setattr("stdout", &buffers.0)?;
+        sys.setattr("stderr", &buffers.1)?;
+
+        Ok(Capture {
+            saved: (saved.0.unbind(), saved.1.unbind()),
+            buffers: (buffers.0.unbind(), buffers.1.unbind()),
+        })
+    }
+
+    fn finish(self, py: Python) -> PyResult<(String, String)> {
+        let sys = py.import_bound("sys")?;
+        sys.setattr("stdout", self.saved.0)?;
+        sys.setattr("stderr", self.saved.1)?;
+
+        Ok((
+            self.buffers.0.call_method0(py, "getvalue")?.extract(py)?,
+            self.buffers.1.call_method0(py, "getvalue")?.extract(py)?,
+        ))
+    }
+}
+
+/// What a test is called with besides fixtures named by its parameters.
+struct Call<'a, 'py> {
+    /// The file the test is in, as fixtures are kept per module.
+    file: &'a str,
+    /// The event loop to run coroutines on.
+    backend: &'a str,
+    scopes: Scopes<'a>,
+    /// Fixtures set up without being passed, as for `usefixtures`.
+    fixtures: &'a [String],
+    /// The parameters of a parametrized test, used instead of fixtures.
+    params: Option<&'a Bound<'py, PyDict>>,
+    /// When the test itself was called, between setting up and tearing down.
+    time: &'a CallTime,
+}
+
+/// When a test was called and when it returned, splitting how long it took
+/// into setup, call and teardown.
+#[derive(Default)]
+struct CallTime {
+    start: Cell<Option<Instant>>,
+    end: Cell<Option<Instant>>,
+}
+
+impl CallTime {
+    fn call<T>(&self, call: impl FnOnce() -> T) -> T {
+        self.start.set(Some(Instant::now()));
+        let result = call();
+        self.end.set(Some(Instant::now()));
+        result
+    }
+
+    /// The durations of a test that started at `started` and has just
+    /// finished. A test that was never called spent all its time in setup.
+    fn durations(&self, started: Instant) -> Durations {
+        let finished = Instant::now();
+        let start = self.start.get().unwrap_or(finished);
+        let end = self.end.get().unwrap_or(start);
+        Durations {
+            setup: start - started,
+            call: end - start,
+            teardown: finished - end,
+        }
+    }
+}
+
+pub fn run_tests(
+    config: &Config,
+    rx: mpsc::Receiver<TestCase>,
+    tx: mpsc::Sender<Event>,
+) -> Result<()> {
+    let mut scope: Option<ModuleScope> = None;
+    let mut session = FixtureScope::default();
+    // The most recent result is held back until its scope is known to be
+    // finished, so errors from teardown hooks can be attributed to it.
+    let mut pending: Option<TestCase> = None;
+    // a test raising KeyboardInterrupt stops the run, as it would pytest
+    let mut interrupted = false;
+    // and so does reaching --maxfail, here or during collection
+    let mut stopped = false;
 
-pub fn run_tests(rx: mpsc::Receiver<TestCase>, tx: mpsc::Sender<TestCase>) -> Result<()> {
     while let Ok(mut test) = rx.recv() {
-        if test.parametrized {
-            // skip parametrized function since they are not supported yet
-            test.passed = false;
-            test.error = Some(PyErr::new::<pyo3::exceptions::PyNotImplementedError, _>(
-                "Parametrized tests are not supported yet".to_string(),
-            ));
-            tx.send(test)?;
+        if config.cancellation.is_cancelled() {
+            break;
+        }
+        if test.deselected {
+            tx.send(Event::Finished(test))?;
             continue;
         }
-        let currrent_dir = env::current_dir().unwrap();
-        let current_dir = Path::new(&currrent_dir);
-        let path_buf = current_dir.join(test.file.clone());
-        let path = path_buf.as_path();
 
-        let mut py_code = fs::read_to_string(path)?;
-        // replace pytest fixture with noop so we can call it directly
-        let s1 = indoc! {"
-        import pytest
-        pytest.fixture = lambda func: func
-        "};
-        py_code.insert_str(0, s1);
-
-        let result = Python::with_gil(|py| -> PyResult<Py<PyAny>> {
-            let syspath = python::setup(&py, current_dir);
-
-            syspath.insert(0, path).unwrap();
-
-            let module = PyModule::from_code_bound(py, &py_code, "", "")?;
-            let function: Py<PyAny> = module.getattr(test.name.as_str())?.into();
-
-            let inspect = py.import_bound("inspect")?;
-            let signature = inspect
-                .getattr("signature")?
-                .call1((module.getattr(test.name.as_str())?,))?;
-            let binding = signature.getattr("parameters")?;
-            let parameters = binding.downcast::<PyMapping>()?;
-
-            // Prepare a vector to hold the positional arguments
-            let mut args_vec: Vec<PyObject> = Vec::new();
-            // Prepare a vector to hold the generators to run after the fixture is called
-            let mut generators: Vec<Py<PyAny>> = Vec::new();
-
-            for item in parameters.items()?.iter()? {
-                let item = item?;
-                let param_name_obj = item.get_item(0)?; // First item is the parameter name
-                let param_name: String = param_name_obj.extract()?;
-                let param_name_py = PyString::new_bound(py, &param_name);
-                // Check if the module has a function with the same name as the parameter
-                if let Ok(func) = module.getattr(param_name_py) {
-                    // If a matching function is found, call it and store the result in args_vec
-                    let value: PyObject = func.call0()?.into();
-                    let value_iter: Result<Py<PyIterator>, PyErr> = value.extract(py);
-                    if value_iter.is_ok() {
-                        // call next on the iterator to get the value
-                        if let Ok(iterator) = value.getattr(py, "__iter__")?.call0(py) {
-                            // Attempt to call __next__ to get the actual value from the generator/iterator
-                            match iterator.getattr(py, "__next__")?.call0(py) {
-                                Ok(next_value) => {
-                                    args_vec.push(next_value);
-                                    generators.push(iterator);
-                                }
-                                Err(err) => {
-                                    return Err(err);
-                                }
-                            }
-                        }
-                    } else {
-                        // If __iter__ doesn't exist, use the value directly
-                        args_vec.push(value);
+        // parametrized tests are looked up without their parameter ID
+        let name = test.name.clone();
+        let (name, id) = match name.split_once('[') {
+            Some((name, id)) => (name, id.strip_suffix(']')),
+            None => (name.as_str(), None),
+        };
+        let (class_name, _) = split_name(name);
+
+        let teardown_started = Instant::now();
+        Python::with_gil(|py| {
+            let module_changed = scope.as_ref().is_some_and(|s| s.file != test.file);
+            let class_changed = scope
+                .as_ref()
+                .is_some_and(|s| s.class.as_ref().map(|c| c.name.as_str()) != class_name);
+
+            if module_changed || class_changed {
+                if let Some(s) = scope.as_mut() {
+                    if let Err(err) = teardown_class(py, s) {
+                        record_error(&mut pending, err);
                     }
-                } else {
-                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-                        "No matching function found for parameter: {}",
-                        param_name
-                    )));
                 }
             }
+            if module_changed {
+                if let Err(err) = teardown_module(py, scope.take().unwrap()) {
+                    record_error(&mut pending, err);
+                }
+            }
+        });
+        record_teardown(&mut pending, teardown_started);
+
+        if let Some(previous) = pending.take() {
+            tx.send(Event::Finished(previous))?;
+        }
 
-            // Create a PyTuple from the arguments vector
-            let args_tuple = PyTuple::new_bound(py, &args_vec);
+        tx.send(Event::Started(test.without_result()))?;
+        let started = Instant::now();
+        let time = CallTime::default();
+        let capture = Python::with_gil(Capture::start)?;
+        let result = Python::with_gil(|py| -> PyResult<bool> {
+            if test.doctest {
+                return time.call(|| run_doctest(py, config, &test)).map(|_| true);
+            }
+
+            let module_scope = match scope {
+                Some(ref mut s) => s,
+                None => scope.insert(setup_module(py, config, &test.file)),
+            };
+            let module = match module_scope.module {
+                Ok(ref module) => module.bind(py).clone(),
+                Err(ref err) => return Err(err.clone_ref(py)),
+            };
+
+            let marks = Marks::evaluate(py, &module, &test.markers)?;
+            if let Some(reason) = marks.skip {
+                test.skipped = Some(reason);
+                return Ok(false);
+            }
+
+            let params = match id {
+                Some(id) if test.parametrized => Some(find_parameters(py, &module, &test, id)?),
+                _ => None,
+            };
+
+            if let Some(XFail {
+                run: false, reason, ..
+            }) = marks.xfail
+            {
+                test.xfail = Some(reason);
+                return Ok(false);
+            }
 
-            let test_result = function.call1(py, args_tuple);
-            // Execute remaining generator items (optional)
-            for generator in generators {
-                while let Ok(_next_item) = generator.getattr(py, "__next__")?.call0(py) {
-                    // just eat the result
+            let (class, class_fixtures) = match class_name {
+                Some(class_name) => {
+                    let class_scope = match module_scope.class {
+                        Some(ref mut c) => c,
+                        None => module_scope
+                            .class
+                            .insert(setup_class(py, &module, class_name)),
+                    };
+                    let class = match class_scope.class {
+                        Ok(ref class) => class.bind(py).clone(),
+                        Err(ref err) => return Err(err.clone_ref(py)),
+                    };
+                    (
+                        Some((class, class_scope.unittest)),
+                        Some(&mut class_scope.fixtures),
+                    )
                 }
+                None => (None, None),
+            };
+            let mut call = Call {
+                file: &test.file,
+                backend: &config.async_backend,
+                scopes: Scopes {
+                    function: FixtureScope::default(),
+                    class: class_fixtures,
+                    module: &mut module_scope.fixtures,
+                    session: &mut session,
+                },
+                fixtures: &marks.fixtures,
+                params: params.as_ref(),
+                time: &time,
+            };
+            let result = match class {
+                Some((class, unittest)) => {
+                    run_method(py, &module, &class, unittest, name, &mut call)
+                }
+                None => run_function(py, &module, name, &mut call),
+            };
+
+            match marks.xfail {
+                Some(xfail) => xfail.outcome(py, &mut test, result),
+                None => result.map(|_| true),
             }
-            test_result
         });
 
-        test.passed = result.is_ok();
+        (test.stdout, test.stderr) = Python::with_gil(|py| capture.finish(py))?;
+        test.durations = time.durations(started);
+        test.duration = started.elapsed();
 
-        match result.is_ok() {
-            true => test.passed = true,
-            false => {
-                test.error = Some(result.err().unwrap());
+        match result {
+            Ok(passed) => test.passed = passed,
+            Err(err) => {
+                interrupted = Python::with_gil(|py| err.is_instance_of::<PyKeyboardInterrupt>(py));
+                test.error = Some(err);
                 test.passed = false;
             }
         }
 
-        tx.send(test)?;
+        stopped = test.failed() && config.cancellation.fail(config.maxfail);
+        pending = Some(test);
+        if interrupted {
+            config.cancellation.cancel();
+        }
+        if interrupted || stopped {
+            break;
+        }
     }
 
+    let teardown_started = Instant::now();
+    Python::with_gil(|py| {
+        if let Some(mut s) = scope {
+            if let Err(err) = teardown_class(py, &mut s) {
+                record_error(&mut pending, err);
+            }
+            if let Err(err) = teardown_module(py, s) {
+                record_error(&mut pending, err);
+            }
+        }
+        if let Err(err) = session.finish(py) {
+            record_error(&mut pending, err);
+        }
+    });
+    record_teardown(&mut pending, teardown_started);
+
+    if let Some(previous) = pending.take() {
+        tx.send(Event::Finished(previous))?;
+    }
+    if interrupted {
+        tx.send(Event::Interrupted("KeyboardInterrupt".to_string()))?;
+    }
+    if stopped {
+        tx.send(Event::Stopped(format!(
+            "stopping after {} failures",
+            config.maxfail
+        )))?;
+    }
+
+    // tests still on their way are dropped, rather than leaving collection
+    // to find the channel closed
+    for _ in rx {}
+
     Ok(())
 }
 
-pub fn get_parametrizations(path: &str, name: &str) -> Result<Vec<String>, PyErr> {
-    let currrent_dir = env::current_dir().unwrap();
-    let current_dir = Path::new(&currrent_dir);
-    let path_buf = current_dir.join(path);
-    let path = path_buf.as_path();
+/// What a test's `skip`, `skipif`, `xfail` and `usefixtures` markers ask
+/// for, evaluated in the test's module.
+#[derive(Default)]
+struct Marks {
+    skip: Option<String>,
+    xfail: Option<XFail>,
+    fixtures: Vec<String>,
+}
+
+struct XFail {
+    reason: String,
+    run: bool,
+    raises: Option<Py<PyAny>>,
+    strict: bool,
+}
+
+impl Marks {
+    /// Evaluates the markers, closest first, so the closest `xfail` wins.
+    fn evaluate(py: Python, module: &Bound<PyModule>, markers: &[Marker]) -> PyResult<Marks> {
+        let mut marks = Marks::default();
+
+        for marker in markers {
+            match marker.name.as_str() {
+                "skip" if marks.skip.is_none() => {
+                    let (args, kwargs) = marker_arguments(py, module, marker)?;
+                    let reason = match kwargs.get_item("reason")? {
+                        Some(reason) => reason.str()?.to_string(),
+                        None if !args.is_empty() => args.get_item(0)?.str()?.to_string(),
+                        None => "unconditional skip".to_string(),
+                    };
+                    marks.skip = Some(reason);
+                }
+                "skipif" if marks.skip.is_none() => {
+                    let (args, kwargs) = marker_arguments(py, module, marker)?;
+                    let reason = match kwargs.get_item("reason")? {
+                        Some(reason) => Some(reason.str()?.to_string()),
+                        None => None,
+                    };
+                    if args.is_empty() {
+                        marks.skip = Some(reason.unwrap_or("unconditional skip".to_string()));
+                        continue;
+                    }
+                    for arg in args.iter() {
+                        if let Some(condition) = evaluate_condition(py, module, &arg)? {
+                            marks.skip =
+                                Some(reason.unwrap_or_else(|| format!("condition: {}", condition)));
+                            break;
+                        }
+                    }
+                }
+                "xfail" if marks.xfail.is_none() => {
+                    let (args, kwargs) = marker_arguments(py, module, marker)?;
+                    let mut applies = args.is_empty();
+                    for arg in args.iter() {
+                        if evaluate_condition(py, module, &arg)?.is_some() {
+                            applies = true;
+                            break;
+                        }
+                    }
+                    if let Some(condition) = kwargs.get_item("condition")? {
+                        applies = evaluate_condition(py, module, &condition)?.is_some();
+                    }
+                    if !applies {
+                        continue;
+                    }
+
+                    marks.xfail = Some(XFail {
+                        reason: match kwargs.get_item("reason")? {
+                            Some(reason) => reason.str()?.to_string(),
+                            None => "".to_string(),
+                        },
+                        run: match kwargs.get_item("run")? {
+                            Some(run) => run.is_truthy()?,
+                            None => true,
+                        },
+                        raises: kwargs
+                            .get_item("raises")?
+                            .filter(|raises| !raises.is_none())
+                            .map(Bound::unbind),
+                        strict: match kwargs.get_item("strict")? {
+                            Some(strict) => strict.is_truthy()?,
+                            None => false,
+                        },
+                    });
+                }
+                "usefixtures" => {
+                    let (args, _) = marker_arguments(py, module, marker)?;
+                    // fixtures from outer scopes are set up first
+                    let names = args
+                        .iter()
+                        .map(|arg| arg.extract())
+                        .collect::<PyResult<Vec<String>>>()?;
+                    marks.fixtures.splice(0..0, names);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(marks)
+    }
+}
+
+impl XFail {
+    /// Decides how a test expected to fail turned out: an unexpected pass
+    /// fails the test only if `strict`, and an exception other than `raises`
+    /// is a real failure.
+    fn outcome(self, py: Python, test: &mut TestCase, result: PyResult<()>) -> PyResult<bool> {
+        match result {
+            Ok(()) if self.strict => Err(PyErr::new::<pyo3::exceptions::PyAssertionError, _>(
+                format!("[XPASS(strict)] {}", self.reason)
+                    .trim_end()
+                    .to_string(),
+            )),
+            Ok(()) => {
+                test.xfail = Some(self.reason);
+                Ok(true)
+            }
+            Err(err) => match self.raises {
+                Some(raises) if !err.is_instance_bound(py, raises.bind(py)) => Err(err),
+                _ => {
+                    test.xfail = Some(self.reason);
+                    Ok(false)
+                }
+            },
+        }
+    }
+}
 
-    let mut py_code = fs::read_to_string(path)?;
-    let s1 = indoc! {"
-    import pytest
-    import itertools
-    def get_parameter_name(obj):
-        if isinstance(obj, list) or isinstance(obj, tuple):
-            return '-'.join([get_parameter_name(o) for o in obj])
-        
-        
-        if hasattr(obj, '__name__'):
-            return obj.__name__
-        else:
-            return str(obj)
-
-    def decorator_factory(argnames, argvalues):
-        def decorator(function):
-            # Generate all parameter combinations if multiple decorators are used
-            if not hasattr(function, 'parameters'):
-                function.parameters = []
-
-            parameters = [get_parameter_name(v) for v in argvalues]
-            if function.parameters:
-                parameters = list(itertools.product(parameters, function.parameters))
-            setattr(function, 'parameters', [get_parameter_name(v) for v in parameters])
-            
-            return function
-        return decorator
-    
-
-    pytest.mark.parametrize =  decorator_factory
-
-    "};
-    py_code.insert_str(0, s1);
-
-    let result = Python::with_gil(|py| -> PyResult<Vec<String>> {
-        let syspath = python::setup(&py, current_dir);
-
-        syspath.insert(0, path).unwrap();
-
-        let module = PyModule::from_code_bound(py, &py_code, "", "");
-        if module.is_err() {
-            return Err(module.err().unwrap());
-        }
-        let function_instance = module.unwrap().clone().getattr(name);
-        if function_instance.is_err() {
-            return Err(function_instance.err().unwrap());
-        }
-        let function: Py<PyAny> = function_instance?.into();
-        function.getattr(py, "parameters").unwrap().extract(py)
+/// Evaluates a marker's arguments in the test's module.
+fn marker_arguments<'py>(
+    py: Python<'py>,
+    module: &Bound<'py, PyModule>,
+    marker: &Marker,
+) -> PyResult<(Bound<'py, PyTuple>, Bound<'py, PyDict>)> {
+    let arguments = marker
+        .args
+        .iter()
+        .cloned()
+        .chain(
+            marker
+                .kwargs
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value)),
+        )
+        .collect::<Vec<String>>()
+        .join(", ");
+    let call = format!("(lambda *args, **kwargs: (args, kwargs))({})", arguments);
+
+    py.eval_bound(&call, Some(&module.dict()), None)?.extract()
+}
+
+/// Evaluates a `skipif` or `xfail` condition, returning its description if
+/// it holds. Strings are evaluated like pytest does, with `os`, `sys` and
+/// `platform` available alongside the module's globals.
+fn evaluate_condition(
+    py: Python,
+    module: &Bound<PyModule>,
+    condition: &Bound<PyAny>,
+) -> PyResult<Option<String>> {
+    if let Ok(expression) = condition.downcast::<PyString>() {
+        let namespace = module.dict().copy()?;
+        for name in ["os", "sys", "platform"] {
+            namespace.set_item(name, py.import_bound(name)?)?;
+        }
+        let expression = expression.to_str()?;
+        let holds = py
+            .eval_bound(expression, Some(&namespace), None)?
+            .is_truthy()?;
+        return Ok(holds.then(|| expression.to_string()));
+    }
+
+    condition
+        .is_truthy()?
+        .then(|| condition.str().map(|s| s.to_string()))
+        .transpose()
+}
+
+/// Splits a test name like `SomeTest::test_something` into its class and
+/// function parts.
+fn split_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once("::") {
+        Some((class, function)) => (Some(class), function),
+        None => (None, name),
+    }
+}
+
+/// Adds the time since `started` spent tearing down scopes to the last test
+/// in them.
+fn record_teardown(test: &mut Option<TestCase>, started: Instant) {
+    if let Some(test) = test {
+        let elapsed = started.elapsed();
+        test.durations.teardown += elapsed;
+        test.duration += elapsed;
+    }
+}
+
+/// Attaches a teardown error to a test that otherwise passed.
+fn record_error(test: &mut Option<TestCase>, err: PyErr) {
+    if let Some(test) = test {
+        if test.error.is_none() {
+            test.passed = false;
+            test.error = Some(err);
+        }
+    }
+}
+
+fn setup_module(py: Python, config: &Config, file: &str) -> ModuleScope {
+    let module = load_module(py, config, file).and_then(|module| {
+        if let Some(hook) = get_hook(module.as_any(), "setup_module") {
+            call_with_optional_argument(py, &hook, module.as_any())?;
+        }
+        Ok(module.unbind())
+    });
+
+    ModuleScope {
+        file: file.to_string(),
+        module,
+        class: None,
+        fixtures: FixtureScope::default(),
+    }
+}
+
+/// Tears down the module's fixtures and then the module, as pytest runs
+/// xunit-style hooks outside fixtures of the same scope.
+fn teardown_module(py: Python, scope: ModuleScope) -> PyResult<()> {
+    let finished = scope.fixtures.finish(py);
+    if let Ok(module) = scope.module {
+        let module = module.bind(py);
+        if let Some(hook) = get_hook(module.as_any(), "teardown_module") {
+            call_with_optional_argument(py, &hook, module.as_any())?;
+        }
+    }
+    finished
+}
+
+fn setup_class(py: Python, module: &Bound<PyModule>, name: &str) -> ClassScope {
+    let mut unittest = false;
+    let class = module.getattr(name).and_then(|class| {
+        let testcase = py.import_bound("unittest")?.getattr("TestCase")?;
+        unittest = class.downcast::<PyType>()?.is_subclass(&testcase)?;
+        if unittest {
+            class.call_method0("setUpClass")?;
+        } else if let Some(hook) = get_hook(&class, "setup_class") {
+            call_with_optional_argument(py, &hook, &class)?;
+        }
+        Ok(class.unbind())
     });
+
+    ClassScope {
+        name: name.to_string(),
+        class,
+        unittest,
+        fixtures: FixtureScope::default(),
+    }
+}
+
+fn teardown_class(py: Python, scope: &mut ModuleScope) -> PyResult<()> {
+    let Some(ClassScope {
+        class,
+        unittest,
+        fixtures,
+        ..
+    }) = scope.class.take()
+    else {
+        return Ok(());
+    };
+
+    let finished = fixtures.finish(py);
+    if let Ok(class) = class {
+        let class = class.bind(py);
+        if unittest {
+            class.call_method0("tearDownClass")?;
+        } else if let Some(hook) = get_hook(class, "teardown_class") {
+            call_with_optional_argument(py, &hook, class)?;
+        }
+    }
+    finished
+}
+
+fn run_function(py: Python, module: &Bound<PyModule>, name: &str, call: &mut Call) -> PyResult<()> {
+    let function = module.getattr(name)?;
+
+    if let Some(hook) = get_hook(module.as_any(), "setup_function") {
+        call_with_optional_argument(py, &hook, &function)?;
+    }
+
+    let mut result = call_with_fixtures(py, module, &function, call);
+
+    if let Some(hook) = get_hook(module.as_any(), "teardown_function") {
+        let teardown = call_with_optional_argument(py, &hook, &function);
+        result = result.and(teardown);
+    }
+
     result
 }
+
+fn run_method(
+    py: Python,
+    module: &Bound<PyModule>,
+    class: &Bound<PyAny>,
+    unittest: bool,
+    name: &str,
+    call: &mut Call,
+) -> PyResult<()> {
+    let (_, method_name) = split_name(name);
+
+    let instance = if unittest {
+        class.call1((method_name,))?
+    } else {
+        class.call0()?
+    };
+    let method = instance.getattr(method_name)?;
+
+    if unittest {
+        instance.call_method0("setUp")?;
+    } else if let Some(hook) = get_hook(&instance, "setup_method") {
+        call_with_optional_argument(py, &hook, &method)?;
+    }
+
+    let mut result = call_with_fixtures(py, module, &method, call);
+
+    let teardown = if unittest {
+        instance.call_method0("tearDown").map(|_| ())
+    } else if let Some(hook) = get_hook(&instance, "teardown_method") {
+        call_with_optional_argument(py, &hook, &method)
+    } else {
+        Ok(())
+    };
+    result = result.and(teardown);
+
+    result
+}
+
+/// Imports a test module and lists the functions in its namespace, along with
+/// the methods of its classes as `(class, unittest, method)`, to find tests
+/// that are created at runtime rather than defined in the source.
+pub fn get_namespace_tests(
+    config: &Config,
+    path: &str,
+) -> PyResult<Vec<(Option<String>, bool, String)>> {
+    Python::with_gil(|py| {
+        let module = load_module(py, config, path)?;
+        let helpers = PyModule::from_code_bound(
+            py,
+            indoc! {"
+            import inspect
+            import unittest
+
+            def namespace_tests(namespace):
+                found = []
+                for name, obj in list(namespace.items()):
+                    if inspect.isfunction(obj):
+                        found.append((None, False, name))
+                    elif inspect.isclass(obj):
+                        is_unittest = issubclass(obj, unittest.TestCase)
+                        for attr, member in vars(obj).items():
+                            if inspect.isfunction(member):
+                                found.append((name, is_unittest, attr))
+                return found
+            "},
+            "",
+            "",
+        )?;
+
+        helpers
+            .getattr("namespace_tests")?
+            .call1((module.dict(),))?
+            .extract()
+    })
+}
+
+/// Runs a doctest with Python's `doctest` runner, finding it in the module
+/// it's named after or parsing it from a text file, failing with the runner's
+/// report if any of its examples fail.
+fn run_doctest(py: Python, config: &Config, test: &TestCase) -> PyResult<()> {
+    python::setup(&py, config);
+    let helpers = PyModule::from_code_bound(
+        py,
+        indoc! {r#"
+        import doctest
+        import importlib
+        import os
+        import sys
+
+        def run_doctest(path, file, module_name, name, text, optionflags):
+            flags = 0
+            for flag in optionflags:
+                if flag not in doctest.OPTIONFLAGS_BY_NAME:
+                    raise ValueError(f"{flag} is not a valid doctest option flag")
+                flags |= doctest.OPTIONFLAGS_BY_NAME[flag]
+
+            if text:
+                with open(path, encoding="utf-8") as f:
+                    parser = doctest.DocTestParser()
+                    test = parser.get_doctest(f.read(), {"__name__": "__main__"}, name, file, 0)
+            else:
+                # import from the directory above the module's outermost package
+                base = os.path.dirname(path)
+                depth = module_name.count(".")
+                if os.path.basename(path) == "__init__.py":
+                    depth += 1
+                for _ in range(depth):
+                    base = os.path.dirname(base)
+                if base not in sys.path:
+                    sys.path.insert(0, base)
+                module = importlib.import_module(module_name)
+                finder = doctest.DocTestFinder()
+                tests = [t for t in finder.find(module, module_name) if t.name == name]
+                if not tests:
+                    raise LookupError(f"doctest not found: {name}")
+                test = tests[0]
+                test.filename = file
+
+            output = []
+            runner = doctest.DocTestRunner(verbose=False, optionflags=flags)
+            result = runner.run(test, out=output.append)
+            if result.failed:
+                raise AssertionError("".join(output).strip())
+        "#},
+        "",
+        "",
+    )?;
+
+    let path = config.rootdir.join(&test.file);
+    helpers.getattr("run_doctest")?.call1((
+        &path,
+        &test.file,
+        doctests::module_name(&path),
+        &test.name,
+        !test.file.ends_with(".py"),
+        config.doctest_optionflags.clone(),
+    ))?;
+    Ok(())
+}
+
+fn load_module<'py>(
+    py: Python<'py>,
+    config: &Config,
+    file: &str,
+) -> PyResult<Bound<'py, PyModule>> {
+    let path_buf = config.rootdir.join(file);
+    let path = path_buf.as_path();
+
+    let py_code = fs::read_to_string(path)?;
+    // replace pytest fixture with one that only records the fixture's scope,
+    // so fixtures can be called directly
+    py.run_bound(
+        indoc! {"
+        import pytest
+
+        def fixture(fixture_function=None, *, scope='function', **kwargs):
+            def record_scope(function):
+                function._rytest_scope = scope
+                return function
+
+            if fixture_function is None:
+                return record_scope
+            return record_scope(fixture_function)
+
+        pytest.fixture = fixture
+        "},
+        None,
+        None,
+    )?;
+
+    let syspath = python::setup(&py, config);
+
+    syspath.insert(0, path).unwrap();
+
+    // compiled with its path so tracebacks point at the test's source
+    PyModule::from_code_bound(py, &py_code, &path.to_string_lossy(), "")
+}
+
+/// Looks up an optional xunit-style hook, treating a missing attribute or
+/// `None` as no hook at all.
+fn get_hook<'py>(holder: &Bound<'py, PyAny>, name: &str) -> Option<Bound<'py, PyAny>> {
+    holder.getattr(name).ok().filter(|hook| !hook.is_none())
+}
+
+/// Calls an xunit-style hook, passing `arg` only if the hook accepts it, as
+/// pytest does for e.g. `def setup_module():` versus `def setup_module(module):`.
+fn call_with_optional_argument(
+    py: Python,
+    func: &Bound<PyAny>,
+    arg: &Bound<PyAny>,
+) -> PyResult<()> {
+    let inspect = py.import_bound("inspect")?;
+    let signature = inspect.getattr("signature")?.call1((func,))?;
+    if signature.getattr("parameters")?.len()? > 0 {
+        func.call1((arg,))?;
+    } else {
+        func.call0()?;
+    }
+    Ok(())
+}
+
+/// Calls a test function, resolving its parameters to fixtures defined in the
+/// module, then tears down its function-scoped fixtures.
+fn call_with_fixtures(
+    py: Python,
+    module: &Bound<PyModule>,
+    function: &Bound<PyAny>,
+    call: &mut Call,
+) -> PyResult<()> {
+    // function-scoped fixtures are torn down even if setting up another fails
+    let mut set_up_and_call = || -> PyResult<()> {
+        let inspect = py.import_bound("inspect")?;
+        let signature = inspect.getattr("signature")?.call1((function,))?;
+        let binding = signature.getattr("parameters")?;
+        let parameters = binding.downcast::<PyMapping>()?;
+
+        // Prepare a vector to hold the positional arguments
+        let mut args_vec: Vec<PyObject> = Vec::new();
+
+        for fixture in call.fixtures {
+            setup_fixture(py, module, call, fixture)?;
+        }
+
+        for item in parameters.items()?.iter()? {
+            let item = item?;
+            let param_name: String = item.get_item(0)?.extract()?; // First item is the parameter name
+            match call
+                .params
+                .map(|params| params.get_item(&param_name))
+                .transpose()?
+                .flatten()
+            {
+                Some(value) => args_vec.push(value.unbind()),
+                None => args_vec.push(setup_fixture(py, module, call, &param_name)?),
+            }
+        }
+
+        // Create a PyTuple from the arguments vector
+        let args_tuple = PyTuple::new_bound(py, &args_vec);
+
+        let runner = call.scopes.function.runner(py, call.backend)?;
+        call.time
+            .call(|| runner.call_method1("call", (function, args_tuple)))
+            .map(|_| ())
+    };
+    let test_result = set_up_and_call();
+    mem::take(&mut call.scopes.function).finish(py)?;
+    test_result
+}
+
+/// Calls the module function named like a fixture and returns its value,
+/// reusing the value already set up in the fixture's scope if there is one.
+/// Generator fixtures are kept in their scope to be finished with it.
+fn setup_fixture(
+    py: Python,
+    module: &Bound<PyModule>,
+    call: &mut Call,
+    name: &str,
+) -> PyResult<PyObject> {
+    let param_name_py = PyString::new_bound(py, name);
+    // Check if the module has a function with the same name as the parameter
+    let Ok(func) = module.getattr(param_name_py) else {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "No matching function found for parameter: {}",
+            name
+        )));
+    };
+
+    // the scope is recorded on the function by the `pytest.fixture` shim
+    let scope: String = match func.getattr("_rytest_scope") {
+        Ok(scope) => scope.extract()?,
+        Err(_) => "function".to_string(),
+    };
+    let key = format!("{}::{}", call.file, name);
+    let backend = call.backend;
+    let fixtures = call.scopes.get(&scope);
+    if let Some(value) = fixtures.values.get(&key) {
+        return Ok(value.clone_ref(py));
+    }
+
+    // If a matching function is found, call it and use the result, awaiting
+    // it for async fixtures on the scope's event loop
+    let runner = fixtures.runner(py, backend)?;
+    let value: PyObject = runner
+        .call_method1("call", (func, PyTuple::empty_bound(py)))?
+        .into();
+    let isasyncgen = py.import_bound("inspect")?.getattr("isasyncgen")?;
+    let value = if isasyncgen.call1((&value,))?.is_truthy()? {
+        let next_value = runner.call_method1("run", (value.call_method0(py, "__anext__")?,))?;
+        fixtures.generators.push(value);
+        next_value.unbind()
+    } else {
+        match value
+            .extract::<Py<PyIterator>>(py)
+            .and_then(|_| value.getattr(py, "__iter__")?.call0(py))
+        {
+            // call next on the iterator to get the value
+            Ok(iterator) => {
+                let next_value = iterator.getattr(py, "__next__")?.call0(py)?;
+                fixtures.generators.push(iterator);
+                next_value
+            }
+            // If __iter__ doesn't exist, use the value directly
+            Err(_) => value,
+        }
+    };
+
+    fixtures.values.insert(key, value.clone_ref(py));
+    Ok(value)
+}
+
+/// Computes parameter IDs by evaluating each `parametrize` marker in the
+/// test's module, for values that can't be worked out from the source.
+pub fn get_parametrizations(
+    config: &Config,
+    path: &str,
+    markers: &[&Marker],
+) -> Result<Vec<String>, PyErr> {
+    Python::with_gil(|py| -> PyResult<Vec<String>> {
+        let module = load_module(py, config, path)?;
+        Ok(parameter_sets(py, &module, markers)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect())
+    })
+}
+
+/// Finds the parameters of a parametrized test from its ID.
+fn find_parameters<'py>(
+    py: Python<'py>,
+    module: &Bound<'py, PyModule>,
+    test: &TestCase,
+    id: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let markers = parametrize::outermost_first(&test.markers);
+    parameter_sets(py, module, &markers)?
+        .into_iter()
+        .find(|(candidate, _)| candidate == id)
+        .map(|(_, params)| params)
+        .ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "No parameters found for [{}]",
+                id
+            ))
+        })
+}
+
+/// Evaluates `parametrize` markers, outermost first, into every combination of
+/// their parameters along with its ID.
+fn parameter_sets<'py>(
+    py: Python<'py>,
+    module: &Bound<'py, PyModule>,
+    markers: &[&Marker],
+) -> PyResult<Vec<(String, Bound<'py, PyDict>)>> {
+    let helpers = PyModule::from_code_bound(
+        py,
+        indoc! {"
+        def get_parameter_name(obj):
+            if isinstance(obj, list) or isinstance(obj, tuple):
+                return '-'.join([get_parameter_name(o) for o in obj])
+
+            if hasattr(obj, '__name__'):
+                return obj.__name__
+            else:
+                return str(obj)
+
+        def parameter_sets(argnames, argvalues):
+            if isinstance(argnames, str):
+                argnames = [name.strip() for name in argnames.split(',') if name.strip()]
+            return [
+                (get_parameter_name(value), dict(zip(argnames, [value] if len(argnames) == 1 else value)))
+                for value in argvalues
+            ]
+        "},
+        "",
+        "",
+    )?;
+
+    let mut sets: Vec<(String, Bound<PyDict>)> = vec![];
+    for (i, marker) in markers.iter().enumerate() {
+        let (args, kwargs) = marker_arguments(py, module, marker)?;
+        let argnames = match kwargs.get_item("argnames")? {
+            Some(argnames) => argnames,
+            None => args.get_item(0)?,
+        };
+        let argvalues = match kwargs.get_item("argvalues")? {
+            Some(argvalues) => argvalues,
+            None => args.get_item(if kwargs.contains("argnames")? { 0 } else { 1 })?,
+        };
+        let inner: Vec<(String, Bound<PyDict>)> = helpers
+            .getattr("parameter_sets")?
+            .call1((argnames, argvalues))?
+            .extract()?;
+
+        if i == 0 {
+            sets = inner;
+            continue;
+        }
+
+        let mut combined = vec![];
+        for (id, params) in &sets {
+            for (inner_id, inner_params) in &inner {
+                let merged = params.copy()?;
+                merged.update(inner_params.as_mapping())?;
+                combined.push((format!("{}-{}", id, inner_id), merged));
+            }
+        }
+        sets = combined;
+    }
+
+    Ok(sets)
+}
@@ -0,0 +1,502 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{traceback, Reporter};
+use crate::{Config, Durations, ExitCode, TestCase};
+
+/// The version of the JSON report and event schema, bumped whenever a field
+/// is removed or changes meaning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How many tests ended up with each outcome.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Summary {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    xfailed: usize,
+    xpassed: usize,
+    errors: usize,
+    deselected: usize,
+}
+
+impl Summary {
+    fn add(&mut self, outcome: &str) {
+        match outcome {
+            "passed" => self.passed += 1,
+            "failed" => self.failed += 1,
+            "skipped" => self.skipped += 1,
+            "xfailed" => self.xfailed += 1,
+            "xpassed" => self.xpassed += 1,
+            "error" => self.errors += 1,
+            _ => self.deselected += 1,
+        }
+    }
+}
+
+/// How long the tests in a file took altogether, to spot slow modules.
+#[derive(Debug, PartialEq, Serialize)]
+struct FileTiming {
+    path: String,
+    tests: usize,
+    duration: f64,
+}
+
+/// Adds a test's duration to its file's, in the order files were run.
+fn add_timing(files: &mut Vec<FileTiming>, test: &TestCase) {
+    if test.deselected {
+        return;
+    }
+    let duration = test.duration.as_secs_f64();
+    match files.iter_mut().find(|file| file.path == test.file) {
+        Some(file) => {
+            file.tests += 1;
+            file.duration += duration;
+        }
+        None => files.push(FileTiming {
+            path: test.file.clone(),
+            tests: 1,
+            duration,
+        }),
+    }
+}
+
+/// How long each phase of a test took, in seconds.
+#[derive(Debug, PartialEq, Serialize)]
+struct Phases {
+    setup: f64,
+    call: f64,
+    teardown: f64,
+}
+
+impl From<Durations> for Phases {
+    fn from(durations: Durations) -> Phases {
+        Phases {
+            setup: durations.setup.as_secs_f64(),
+            call: durations.call.as_secs_f64(),
+            teardown: durations.teardown.as_secs_f64(),
+        }
+    }
+}
+
+/// A test and its result, as it appears in the report and event stream.
+#[derive(Debug, PartialEq, Serialize)]
+struct TestReport {
+    nodeid: String,
+    outcome: &'static str,
+    duration: f64,
+    phases: Phases,
+    markers: Vec<String>,
+    /// Why the test was skipped or expected to fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    traceback: Option<String>,
+    stdout: String,
+    stderr: String,
+}
+
+impl TestReport {
+    fn new(test: &TestCase) -> TestReport {
+        TestReport {
+            nodeid: nodeid(&test.file, &test.name),
+            outcome: outcome(test),
+            duration: test.duration.as_secs_f64(),
+            phases: test.durations.into(),
+            markers: marker_names(test),
+            reason: test.skipped.clone().or(test.xfail.clone()),
+            message: test.error.as_ref().map(|e| e.to_string()),
+            traceback: test.error.as_ref().map(traceback),
+            stdout: test.stdout.clone(),
+            stderr: test.stderr.clone(),
+        }
+    }
+
+    /// A test that couldn't be collected.
+    fn error(test: &TestCase) -> TestReport {
+        TestReport {
+            outcome: "error",
+            ..TestReport::new(test)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    schema_version: u32,
+    rytest_version: &'static str,
+    rootdir: &'a str,
+    exitcode: i32,
+    duration: f64,
+    summary: &'a Summary,
+    files: &'a [FileTiming],
+    tests: &'a [TestReport],
+}
+
+/// A line of the event stream, tagged with the kind of event it is.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Record<'a> {
+    SessionStart {
+        schema_version: u32,
+        rytest_version: &'static str,
+        rootdir: &'a str,
+    },
+    Collected {
+        nodeid: String,
+        file: &'a str,
+        markers: Vec<String>,
+        deselected: bool,
+    },
+    TestStart {
+        nodeid: String,
+    },
+    TestOutcome(&'a TestReport),
+    SessionFinish {
+        exitcode: i32,
+        duration: f64,
+        summary: &'a Summary,
+        files: &'a [FileTiming],
+    },
+}
+
+/// Writes a single JSON document with every result once the run is over.
+pub struct JsonReport {
+    path: PathBuf,
+    rootdir: String,
+    summary: Summary,
+    files: Vec<FileTiming>,
+    tests: Vec<TestReport>,
+}
+
+impl JsonReport {
+    pub fn new(path: &Path, config: &Config) -> JsonReport {
+        JsonReport {
+            path: path.to_path_buf(),
+            rootdir: config.rootdir.display().to_string(),
+            summary: Summary::default(),
+            files: vec![],
+            tests: vec![],
+        }
+    }
+}
+
+impl Reporter for JsonReport {
+    fn collected(&mut self, test: &TestCase) -> Result<()> {
+        if test.error.is_some() {
+            let report = TestReport::error(test);
+            self.summary.add(report.outcome);
+            self.tests.push(report);
+        }
+        Ok(())
+    }
+
+    fn test_finish(&mut self, result: &TestCase) -> Result<()> {
+        let report = TestReport::new(result);
+        self.summary.add(report.outcome);
+        add_timing(&mut self.files, result);
+        self.tests.push(report);
+        Ok(())
+    }
+
+    fn session_finish(&mut self, duration: Duration, exit_code: ExitCode) -> Result<()> {
+        let document = Document {
+            schema_version: SCHEMA_VERSION,
+            rytest_version: env!("CARGO_PKG_VERSION"),
+            rootdir: &self.rootdir,
+            exitcode: exit_code as i32,
+            duration: duration.as_secs_f64(),
+            summary: &self.summary,
+            files: &self.files,
+            tests: &self.tests,
+        };
+        serde_json::to_writer_pretty(BufWriter::new(create(&self.path)?), &document)?;
+
+        Ok(())
+    }
+}
+
+/// Writes events as JSON Lines while the tests run, to a file or `-` for
+/// stdout, flushing each one so it can be followed live.
+pub struct EventStream {
+    out: Box<dyn Write + Send>,
+    rootdir: String,
+    summary: Summary,
+    files: Vec<FileTiming>,
+}
+
+impl EventStream {
+    pub fn new(target: &str, config: &Config) -> Result<EventStream> {
+        let out: Box<dyn Write + Send> = match target {
+            "-" => Box::new(io::stdout()),
+            path => Box::new(BufWriter::new(create(Path::new(path))?)),
+        };
+
+        Ok(EventStream {
+            out,
+            rootdir: config.rootdir.display().to_string(),
+            summary: Summary::default(),
+            files: vec![],
+        })
+    }
+
+    fn write(&mut self, record: &Record) -> Result<()> {
+        serde_json::to_writer(&mut self.out, record)?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Reporter for EventStream {
+    fn session_start(&mut self) -> Result<()> {
+        let rootdir = self.rootdir.clone();
+        self.write(&Record::SessionStart {
+            schema_version: SCHEMA_VERSION,
+            rytest_version: env!("CARGO_PKG_VERSION"),
+            rootdir: &rootdir,
+        })
+    }
+
+    fn collected(&mut self, test: &TestCase) -> Result<()> {
+        self.write(&Record::Collected {
+            nodeid: nodeid(&test.file, &test.name),
+            file: &test.file,
+            markers: marker_names(test),
+            deselected: test.deselected,
+        })?;
+        if test.error.is_some() {
+            let report = TestReport::error(test);
+            self.summary.add(report.outcome);
+            self.write(&Record::TestOutcome(&report))?;
+        }
+
+        Ok(())
+    }
+
+    fn test_start(&mut self, test: &TestCase) -> Result<()> {
+        self.write(&Record::TestStart {
+            nodeid: nodeid(&test.file, &test.name),
+        })
+    }
+
+    fn test_finish(&mut self, result: &TestCase) -> Result<()> {
+        let report = TestReport::new(result);
+        self.summary.add(report.outcome);
+        add_timing(&mut self.files, result);
+        self.write(&Record::TestOutcome(&report))
+    }
+
+    fn session_finish(&mut self, duration: Duration, exit_code: ExitCode) -> Result<()> {
+        let summary = std::mem::take(&mut self.summary);
+        let files = std::mem::take(&mut self.files);
+        self.write(&Record::SessionFinish {
+            exitcode: exit_code as i32,
+            duration: duration.as_secs_f64(),
+            summary: &summary,
+            files: &files,
+        })
+    }
+}
+
+fn create(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(File::create(path)?)
+}
+
+/// A file that couldn't be collected is reported by its path alone.
+fn nodeid(file: &str, name: &str) -> String {
+    if name.is_empty() {
+        file.to_string()
+    } else {
+        format!("{}::{}", file, name)
+    }
+}
+
+fn marker_names(test: &TestCase) -> Vec<String> {
+    test.markers.iter().map(|m| m.name.clone()).collect()
+}
+
+fn outcome(test: &TestCase) -> &'static str {
+    match (&test.skipped, &test.xfail) {
+        _ if test.deselected => "deselected",
+        (Some(_), _) => "skipped",
+        (None, Some(_)) if test.passed => "xpassed",
+        (None, Some(_)) => "xfailed",
+        (None, None) if test.passed => "passed",
+        (None, None) => "failed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Marker;
+    use pyo3::exceptions::PyAssertionError;
+    use pyo3::PyErr;
+
+    fn test_case(name: &str) -> TestCase {
+        TestCase {
+            passed: true,
+            duration: Duration::from_millis(250),
+            durations: Durations {
+                setup: Duration::from_millis(50),
+                call: Duration::from_millis(125),
+                teardown: Duration::from_millis(75),
+            },
+            ..TestCase::new("test_file.py", name)
+        }
+    }
+
+    #[test]
+    fn test_outcome() {
+        let mut skipped = test_case("test_skipped");
+        skipped.passed = false;
+        skipped.skipped = Some("".to_string());
+        let mut xfailed = test_case("test_xfailed");
+        xfailed.passed = false;
+        xfailed.xfail = Some("".to_string());
+        let mut xpassed = test_case("test_xpassed");
+        xpassed.xfail = Some("".to_string());
+        let mut failed = test_case("test_failed");
+        failed.passed = false;
+        let mut deselected = test_case("test_deselected");
+        deselected.deselected = true;
+
+        let outcomes: Vec<&str> = [
+            test_case("test_passed"),
+            skipped,
+            xfailed,
+            xpassed,
+            failed,
+            deselected,
+        ]
+        .iter()
+        .map(outcome)
+        .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                "passed",
+                "skipped",
+                "xfailed",
+                "xpassed",
+                "failed",
+                "deselected"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collection_error() {
+        let mut report = JsonReport::new(Path::new("report.json"), &Config::default());
+        let mut broken = test_case("");
+        broken.passed = false;
+        broken.error = Some(PyErr::new::<PyAssertionError, _>("broken"));
+        report.collected(&broken).unwrap();
+        report.collected(&test_case("test_collected")).unwrap();
+
+        assert_eq!(report.tests.len(), 1);
+        assert_eq!(report.tests[0].nodeid, "test_file.py");
+        assert_eq!(report.tests[0].outcome, "error");
+        assert_eq!(report.summary.errors, 1);
+    }
+
+    #[test]
+    fn test_add_timing() {
+        let mut other = test_case("test_other");
+        other.file = "test_other.py".to_string();
+        let mut deselected = test_case("test_deselected");
+        deselected.deselected = true;
+
+        let mut files = vec![];
+        for test in [
+            test_case("test_one"),
+            other,
+            test_case("test_two"),
+            deselected,
+        ] {
+            add_timing(&mut files, &test);
+        }
+        assert_eq!(
+            files,
+            vec![
+                FileTiming {
+                    path: "test_file.py".to_string(),
+                    tests: 2,
+                    duration: 0.5,
+                },
+                FileTiming {
+                    path: "test_other.py".to_string(),
+                    tests: 1,
+                    duration: 0.25,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collected_record() {
+        let mut test = test_case("test_slow");
+        test.deselected = true;
+        test.markers = vec![Marker {
+            name: "slow".to_string(),
+            args: vec![],
+            kwargs: vec![],
+        }];
+        let record = Record::Collected {
+            nodeid: nodeid(&test.file, &test.name),
+            file: &test.file,
+            markers: marker_names(&test),
+            deselected: test.deselected,
+        };
+
+        assert_eq!(
+            serde_json::to_value(record).unwrap(),
+            serde_json::json!({
+                "event": "collected",
+                "nodeid": "test_file.py::test_slow",
+                "file": "test_file.py",
+                "markers": ["slow"],
+                "deselected": true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_test_report() {
+        let mut test = test_case("TestClass::test_fails[1]");
+        test.passed = false;
+        test.error = Some(PyErr::new::<PyAssertionError, _>("1 != 2"));
+        test.markers = vec![Marker {
+            name: "parametrize".to_string(),
+            args: vec![],
+            kwargs: vec![],
+        }];
+        test.stdout = "printed\n".to_string();
+
+        assert_eq!(
+            serde_json::to_value(TestReport::new(&test)).unwrap(),
+            serde_json::json!({
+                "nodeid": "test_file.py::TestClass::test_fails[1]",
+                "outcome": "failed",
+                "duration": 0.25,
+                "phases": {"setup": 0.05, "call": 0.125, "teardown": 0.075},
+                "markers": ["parametrize"],
+                "message": "AssertionError: 1 != 2",
+                "traceback": "AssertionError: 1 != 2\n",
+                "stdout": "printed\n",
+                "stderr": "",
+            })
+        );
+    }
+}
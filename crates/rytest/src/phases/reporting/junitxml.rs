@@ -0,0 +1,342 @@
+use anyhow::Result;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{traceback, Reporter};
+use crate::{ExitCode, TestCase};
+
+/// The tests of one file, reported as a `<testsuite>` with the time they took
+/// altogether.
+#[derive(Default)]
+struct Suite {
+    file: String,
+    cases: String,
+    tests: usize,
+    errors: usize,
+    failures: usize,
+    skipped: usize,
+    time: Duration,
+}
+
+/// Writes a JUnit XML report of the results in the `junit_family` flavour
+/// pytest would, where `xunit1` (or `legacy`) also gives the file of each test.
+/// Each file gets its own `<testsuite>`, in the order files were run.
+pub struct JUnitXml {
+    path: PathBuf,
+    family: String,
+    suites: Vec<Suite>,
+}
+
+impl JUnitXml {
+    pub fn new(path: &Path, family: &str) -> JUnitXml {
+        JUnitXml {
+            path: path.to_path_buf(),
+            family: family.to_string(),
+            suites: vec![],
+        }
+    }
+
+    fn suite(&mut self, file: &str) -> &mut Suite {
+        let index = match self.suites.iter().position(|suite| suite.file == file) {
+            Some(index) => index,
+            None => {
+                self.suites.push(Suite {
+                    file: file.to_string(),
+                    ..Suite::default()
+                });
+                self.suites.len() - 1
+            }
+        };
+        &mut self.suites[index]
+    }
+
+    /// Adds a test to the report, as an error if it couldn't be collected.
+    fn add(&mut self, result: &TestCase, collection_error: bool) {
+        let xunit1 = self.family != "xunit2";
+        let suite = self.suite(&result.file);
+        suite.tests += 1;
+        suite.time += result.duration;
+        let cases = &mut suite.cases;
+        let (classname, name) = names(result);
+        write!(
+            cases,
+            r#"    <testcase classname="{}" name="{}""#,
+            escape(&classname),
+            escape(&name)
+        )
+        .unwrap();
+        if xunit1 {
+            write!(cases, r#" file="{}""#, escape(&result.file)).unwrap();
+        }
+        writeln!(cases, r#" time="{:.3}">"#, result.duration.as_secs_f64()).unwrap();
+
+        match (&result.skipped, &result.xfail, &result.error) {
+            (Some(reason), _, _) if !collection_error => {
+                suite.skipped += 1;
+                writeln!(
+                    cases,
+                    r#"      <skipped type="pytest.skip" message="{}" />"#,
+                    escape(reason)
+                )
+                .unwrap();
+            }
+            (None, Some(reason), _) if !result.passed => {
+                suite.skipped += 1;
+                writeln!(
+                    cases,
+                    r#"      <skipped type="pytest.xfail" message="{}" />"#,
+                    escape(reason)
+                )
+                .unwrap();
+            }
+            (_, _, Some(error)) if collection_error => {
+                suite.errors += 1;
+                writeln!(
+                    cases,
+                    r#"      <error message="collection failure">{}</error>"#,
+                    escape(&traceback(error))
+                )
+                .unwrap();
+            }
+            (None, _, error) if !result.passed => {
+                suite.failures += 1;
+                let (message, details) = match error {
+                    Some(error) => (error.to_string(), traceback(error)),
+                    None => ("failed".to_string(), String::new()),
+                };
+                writeln!(
+                    cases,
+                    r#"      <failure message="{}">{}</failure>"#,
+                    escape(message.lines().next().unwrap_or_default()),
+                    escape(&details)
+                )
+                .unwrap();
+            }
+            _ => {}
+        }
+
+        for (tag, output) in [
+            ("system-out", &result.stdout),
+            ("system-err", &result.stderr),
+        ] {
+            if !output.is_empty() {
+                writeln!(cases, "      <{tag}>{}</{tag}>", escape(output)).unwrap();
+            }
+        }
+        writeln!(cases, "    </testcase>").unwrap();
+    }
+
+    fn document(&self, duration: Duration) -> String {
+        let mut suites = String::new();
+        for suite in &self.suites {
+            writeln!(
+                suites,
+                r#"  <testsuite name="{}" errors="{}" failures="{}" skipped="{}" tests="{}" time="{:.3}">"#,
+                escape(&suite.file),
+                suite.errors,
+                suite.failures,
+                suite.skipped,
+                suite.tests,
+                suite.time.as_secs_f64()
+            )
+            .unwrap();
+            suites.push_str(&suite.cases);
+            suites.push_str("  </testsuite>\n");
+        }
+
+        let total = |count: fn(&Suite) -> usize| self.suites.iter().map(count).sum::<usize>();
+        format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                "\n",
+                r#"<testsuites name="pytest" errors="{}" failures="{}" skipped="{}" tests="{}" time="{:.3}">"#,
+                "\n{}</testsuites>\n"
+            ),
+            total(|suite| suite.errors),
+            total(|suite| suite.failures),
+            total(|suite| suite.skipped),
+            total(|suite| suite.tests),
+            duration.as_secs_f64(),
+            suites
+        )
+    }
+}
+
+impl Reporter for JUnitXml {
+    fn collected(&mut self, test: &TestCase) -> Result<()> {
+        if test.error.is_some() {
+            self.add(test, true);
+        }
+        Ok(())
+    }
+
+    fn test_finish(&mut self, result: &TestCase) -> Result<()> {
+        if !result.deselected {
+            self.add(result, false);
+        }
+        Ok(())
+    }
+
+    fn session_finish(&mut self, duration: Duration, _exit_code: ExitCode) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, self.document(duration))?;
+
+        Ok(())
+    }
+}
+
+/// The `classname` and `name` of a test, as pytest derives them from its node
+/// ID: the module as a dotted path followed by any class, then the test.
+fn names(result: &TestCase) -> (String, String) {
+    let module = result.file.strip_suffix(".py").unwrap_or(&result.file);
+    let module = module.replace(['/', '\\'], ".");
+    if result.name.is_empty() {
+        return (String::new(), module);
+    }
+
+    let mut parts: Vec<&str> = result.name.split("::").collect();
+    let name = parts.pop().unwrap_or_default().to_string();
+    let classname = std::iter::once(module.as_str())
+        .chain(parts)
+        .collect::<Vec<_>>()
+        .join(".");
+    (classname, name)
+}
+
+/// Escapes text for XML, replacing control characters that XML doesn't allow
+/// the way pytest does.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => write!(escaped, "#x{:02X}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::exceptions::{PyAssertionError, PySyntaxError};
+    use pyo3::PyErr;
+
+    fn test_case(file: &str, name: &str) -> TestCase {
+        TestCase {
+            passed: true,
+            duration: Duration::from_millis(12),
+            ..TestCase::new(file, name)
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            names(&test_case("tests/test_file.py", "test_one[1-a]")),
+            ("tests.test_file".to_string(), "test_one[1-a]".to_string())
+        );
+        assert_eq!(
+            names(&test_case("tests/test_file.py", "TestClass::test_method")),
+            (
+                "tests.test_file.TestClass".to_string(),
+                "test_method".to_string()
+            )
+        );
+        assert_eq!(
+            names(&test_case("tests/test_file.py", "")),
+            ("".to_string(), "tests.test_file".to_string())
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("assert \"a\" < 'b' & c\x1b\n"),
+            "assert &quot;a&quot; &lt; 'b' &amp; c#x1B\n"
+        );
+    }
+
+    fn report(family: &str, results: &[TestCase], duration: Duration) -> String {
+        let mut junitxml = JUnitXml::new(Path::new("report.xml"), family);
+        for result in results {
+            if result.error.is_some() && result.name.is_empty() {
+                junitxml.collected(result).unwrap();
+            } else {
+                junitxml.test_finish(result).unwrap();
+            }
+        }
+        junitxml.document(duration)
+    }
+
+    #[test]
+    fn test_report() {
+        let mut failed = test_case("test_file.py", "test_fails");
+        failed.passed = false;
+        failed.error = Some(PyErr::new::<PyAssertionError, _>("1 != 2"));
+        failed.stdout = "printed\n".to_string();
+        let mut skipped = test_case("test_file.py", "TestClass::test_skipped");
+        skipped.passed = false;
+        skipped.skipped = Some("not today".to_string());
+        let mut xfailed = test_case("test_file.py", "test_xfailed");
+        xfailed.passed = false;
+        xfailed.xfail = Some("known bug".to_string());
+        let mut broken = test_case("test_broken.py", "");
+        broken.passed = false;
+        broken.error = Some(PyErr::new::<PySyntaxError, _>("invalid syntax"));
+        let results = [
+            test_case("test_file.py", "test_passes"),
+            failed,
+            skipped,
+            xfailed,
+            broken,
+        ];
+
+        assert_eq!(
+            report("xunit2", &results, Duration::from_millis(1500)),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+                "<testsuites name=\"pytest\" errors=\"1\" failures=\"1\" skipped=\"2\" tests=\"5\" time=\"1.500\">\n",
+                "  <testsuite name=\"test_file.py\" errors=\"0\" failures=\"1\" skipped=\"2\" tests=\"4\" time=\"0.048\">\n",
+                "    <testcase classname=\"test_file\" name=\"test_passes\" time=\"0.012\">\n",
+                "    </testcase>\n",
+                "    <testcase classname=\"test_file\" name=\"test_fails\" time=\"0.012\">\n",
+                "      <failure message=\"AssertionError: 1 != 2\">AssertionError: 1 != 2\n</failure>\n",
+                "      <system-out>printed\n</system-out>\n",
+                "    </testcase>\n",
+                "    <testcase classname=\"test_file.TestClass\" name=\"test_skipped\" time=\"0.012\">\n",
+                "      <skipped type=\"pytest.skip\" message=\"not today\" />\n",
+                "    </testcase>\n",
+                "    <testcase classname=\"test_file\" name=\"test_xfailed\" time=\"0.012\">\n",
+                "      <skipped type=\"pytest.xfail\" message=\"known bug\" />\n",
+                "    </testcase>\n",
+                "  </testsuite>\n",
+                "  <testsuite name=\"test_broken.py\" errors=\"1\" failures=\"0\" skipped=\"0\" tests=\"1\" time=\"0.012\">\n",
+                "    <testcase classname=\"\" name=\"test_broken\" time=\"0.012\">\n",
+                "      <error message=\"collection failure\">SyntaxError: invalid syntax\n</error>\n",
+                "    </testcase>\n",
+                "  </testsuite>\n",
+                "</testsuites>\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_report_xunit1() {
+        let results = [test_case("tests/test_file.py", "test_passes")];
+
+        assert!(report("xunit1", &results, Duration::ZERO).contains(
+            r#"<testcase classname="tests.test_file" name="test_passes" file="tests/test_file.py" time="0.012">"#
+        ));
+        assert!(!report("xunit2", &results, Duration::ZERO).contains("file="));
+    }
+}
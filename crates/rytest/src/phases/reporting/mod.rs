@@ -0,0 +1,280 @@
+use anyhow::Result;
+use pyo3::prelude::*;
+use pyo3::PyErr;
+use std::time::Duration;
+use std::{sync::mpsc, time::Instant};
+
+use crate::{Config, Event, ExitCode, TestCase};
+
+mod json;
+mod junitxml;
+mod terminal;
+
+pub use json::{EventStream, JsonReport};
+pub use junitxml::JUnitXml;
+pub use terminal::Terminal;
+
+/// Receives a run as it happens: each test as it's collected, starts and
+/// finishes, then the end of the session. Every callback does nothing by
+/// default, so a reporter only implements the ones it needs.
+pub trait Reporter: Send {
+    /// Called once, before anything is collected.
+    fn session_start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// A test was collected, without a result yet. It won't be run if it was
+    /// deselected, or if it has an error because it couldn't be collected.
+    fn collected(&mut self, _test: &TestCase) -> Result<()> {
+        Ok(())
+    }
+
+    /// Every test has been collected, though some may already have finished.
+    fn collection_finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// A test is about to run.
+    fn test_start(&mut self, _test: &TestCase) -> Result<()> {
+        Ok(())
+    }
+
+    /// A test finished, or was deselected, with its result.
+    fn test_finish(&mut self, _result: &TestCase) -> Result<()> {
+        Ok(())
+    }
+
+    /// The run was stopped before every test had run, by an interruption or
+    /// `--maxfail`, with why.
+    fn interrupted(&mut self, _reason: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once every test has finished, with how long the run took and
+    /// the exit code it ended with.
+    fn session_finish(&mut self, _duration: Duration, _exit_code: ExitCode) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The reporters asked for by the configuration: the report files, then the
+/// terminal output, unless JSON Lines are streamed to stdout in its place.
+pub fn reporters(config: &Config) -> Result<Vec<Box<dyn Reporter>>> {
+    let mut reporters: Vec<Box<dyn Reporter>> = vec![];
+    if let Some(path) = &config.junitxml {
+        reporters.push(Box::new(JUnitXml::new(path, &config.junit_family)));
+    }
+    if let Some(path) = &config.report_json {
+        reporters.push(Box::new(JsonReport::new(path, config)));
+    }
+    if let Some(target) = &config.report_jsonl {
+        reporters.push(Box::new(EventStream::new(target, config)?));
+    }
+    if config.report_jsonl.as_deref() != Some("-") {
+        reporters.push(Box::new(Terminal::new(config)));
+    }
+
+    Ok(reporters)
+}
+
+/// Passes each event of a run on to every reporter, in order, and works out
+/// the exit code from them.
+pub fn report(
+    rx: mpsc::Receiver<Event>,
+    mut reporters: Vec<Box<dyn Reporter>>,
+    start: Instant,
+) -> Result<ExitCode> {
+    for reporter in reporters.iter_mut() {
+        reporter.session_start()?;
+    }
+
+    let mut collected = 0;
+    let mut failed = false;
+    let mut interrupted = false;
+    while let Ok(event) = rx.recv() {
+        match &event {
+            Event::Collected(test) if test.error.is_some() => failed = true,
+            Event::Collected(test) if !test.deselected => collected += 1,
+            Event::Finished(result) if result.failed() => failed = true,
+            Event::Interrupted(_) => interrupted = true,
+            _ => {}
+        }
+
+        for reporter in reporters.iter_mut() {
+            match &event {
+                Event::Collected(test) => reporter.collected(test)?,
+                Event::CollectionFinished => reporter.collection_finish()?,
+                Event::Started(test) => reporter.test_start(test)?,
+                Event::Finished(result) => reporter.test_finish(result)?,
+                Event::Interrupted(reason) | Event::Stopped(reason) => {
+                    reporter.interrupted(reason)?
+                }
+            }
+        }
+    }
+
+    let exit_code = match () {
+        _ if interrupted => ExitCode::Interrupted,
+        _ if failed => ExitCode::TestsFailed,
+        _ if collected == 0 => ExitCode::NoTestsCollected,
+        _ => ExitCode::Ok,
+    };
+    let duration = start.elapsed();
+    for reporter in reporters.iter_mut() {
+        reporter.session_finish(duration, exit_code)?;
+    }
+
+    Ok(exit_code)
+}
+
+/// The Python traceback of an error, or just the error if it has none.
+fn traceback(error: &PyErr) -> String {
+    Python::with_gil(|py| -> PyResult<String> {
+        let lines: Vec<String> = py
+            .import_bound("traceback")?
+            .call_method1(
+                "format_exception",
+                (
+                    error.get_type_bound(py),
+                    error.value_bound(py),
+                    error.traceback_bound(py),
+                ),
+            )?
+            .extract()?;
+        Ok(lines.concat())
+    })
+    .unwrap_or_else(|_| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the callbacks it receives, tagged with its own name.
+    struct Recorder {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn record(&self, call: String) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{}: {}", self.name, call));
+        }
+    }
+
+    impl Reporter for Recorder {
+        fn session_start(&mut self) -> Result<()> {
+            self.record("session_start".to_string());
+            Ok(())
+        }
+
+        fn collected(&mut self, test: &TestCase) -> Result<()> {
+            self.record(format!("collected {}", test.name));
+            Ok(())
+        }
+
+        fn test_finish(&mut self, result: &TestCase) -> Result<()> {
+            self.record(format!("test_finish {} {}", result.name, result.passed));
+            Ok(())
+        }
+
+        fn session_finish(&mut self, _duration: Duration, exit_code: ExitCode) -> Result<()> {
+            self.record(format!("session_finish {:?}", exit_code));
+            Ok(())
+        }
+    }
+
+    fn exit_code(events: Vec<Event>) -> ExitCode {
+        let (tx, rx) = mpsc::channel();
+        for event in events {
+            tx.send(event).unwrap();
+        }
+        drop(tx);
+        report(rx, vec![], Instant::now()).unwrap()
+    }
+
+    #[test]
+    fn test_report_exit_code() {
+        let mut passed = TestCase::new("test_file.py", "test_passed");
+        passed.passed = true;
+        let mut skipped = TestCase::new("test_file.py", "test_skipped");
+        skipped.skipped = Some("".to_string());
+        let mut deselected = TestCase::new("test_file.py", "test_deselected");
+        deselected.deselected = true;
+        let mut broken = TestCase::new("test_file.py", "");
+        broken.error = Some(PyErr::new::<pyo3::exceptions::PySyntaxError, _>("invalid"));
+
+        assert_eq!(
+            exit_code(vec![
+                Event::Collected(passed.without_result()),
+                Event::Finished(passed),
+                Event::Finished(skipped),
+            ]),
+            ExitCode::Ok
+        );
+        assert_eq!(
+            exit_code(vec![
+                Event::Collected(TestCase::new("test_file.py", "test_failed")),
+                Event::Finished(TestCase::new("test_file.py", "test_failed")),
+            ]),
+            ExitCode::TestsFailed
+        );
+        assert_eq!(
+            exit_code(vec![Event::Collected(broken)]),
+            ExitCode::TestsFailed
+        );
+        assert_eq!(
+            exit_code(vec![Event::Interrupted("KeyboardInterrupt".to_string())]),
+            ExitCode::Interrupted
+        );
+        assert_eq!(
+            exit_code(vec![
+                Event::Collected(deselected.without_result()),
+                Event::Finished(deselected),
+            ]),
+            ExitCode::NoTestsCollected
+        );
+    }
+
+    #[test]
+    fn test_report_calls_every_reporter() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let reporters: Vec<Box<dyn Reporter>> = vec![
+            Box::new(Recorder {
+                name: "first",
+                calls: calls.clone(),
+            }),
+            Box::new(Recorder {
+                name: "second",
+                calls: calls.clone(),
+            }),
+        ];
+
+        let (tx, rx) = mpsc::channel();
+        let mut result = TestCase::new("test_file.py", "test_one");
+        tx.send(Event::Collected(result.without_result())).unwrap();
+        tx.send(Event::Started(result.without_result())).unwrap();
+        result.passed = true;
+        tx.send(Event::Finished(result)).unwrap();
+        drop(tx);
+        assert_eq!(report(rx, reporters, Instant::now()).unwrap(), ExitCode::Ok);
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "first: session_start",
+                "second: session_start",
+                "first: collected test_one",
+                "second: collected test_one",
+                "first: test_finish test_one true",
+                "second: test_finish test_one true",
+                "first: session_finish Ok",
+                "second: session_finish Ok",
+            ]
+        );
+    }
+}
@@ -0,0 +1,695 @@
+use anyhow::Result;
+use colored::{ColoredString, Colorize};
+use std::borrow::Cow;
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{traceback, Reporter};
+use crate::{Config, ExitCode, TestCase};
+
+/// The width of the ` [100%]` progress column.
+const PROGRESS_WIDTH: usize = 7;
+
+/// Prints the collected tests, or the progress of the run followed by a
+/// summary, like pytest does at each verbosity:
+///
+/// - `-qq`: only failures and the summary
+/// - `-q`: a character per test, then failures and the summary
+/// - default: a line per file with a character per test
+/// - `-v`: a line per test, with failures shown as they happen
+/// - `-vv`: the same, with full tracebacks
+#[derive(Default)]
+pub struct Terminal {
+    collect_only: bool,
+    durations: Option<usize>,
+    durations_min: Duration,
+    junitxml: Option<PathBuf>,
+    report_chars: String,
+    verbosity: i8,
+    width: usize,
+    collected: usize,
+    errors: usize,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    xfailed: usize,
+    xpassed: usize,
+    deselected: usize,
+    /// Whether every test has been collected, so progress can be shown as a
+    /// percentage. Until then, finished tests are held back.
+    collection_finished: bool,
+    held: Vec<Line>,
+    shown: usize,
+    /// The file whose tests are being shown on the current line, and how far
+    /// along the line they are.
+    file: Option<String>,
+    column: usize,
+    /// Tests that couldn't be collected, and failures shown after the
+    /// progress rather than as they happen.
+    collection_errors: Vec<(String, String)>,
+    failures: Vec<(String, String)>,
+    /// Passed tests that printed something, shown with `-rP`.
+    passes: Vec<(String, String)>,
+    /// The tests listed in the short test summary.
+    outcomes: Vec<Outcome>,
+    /// How long each phase of each test took, with `--durations`.
+    timings: Vec<(Duration, &'static str, String)>,
+    /// Why the run was stopped early.
+    interrupted: Option<String>,
+}
+
+/// A test as it's listed in the short test summary, by the `-r` character of
+/// its outcome.
+struct Outcome {
+    char: char,
+    file: String,
+    nodeid: String,
+    /// The first line of the error, or why the test was skipped or expected
+    /// to fail.
+    message: String,
+}
+
+/// How a finished test is shown.
+struct Line {
+    file: String,
+    nodeid: String,
+    letter: ColoredString,
+    outcome: ColoredString,
+    /// The error and captured output of a failed test.
+    failure: Option<String>,
+}
+
+impl Terminal {
+    pub fn new(config: &Config) -> Terminal {
+        Terminal {
+            collect_only: config.collect_only,
+            durations: config.durations,
+            durations_min: config.durations_min,
+            junitxml: config.junitxml.clone(),
+            report_chars: config.report_chars.clone(),
+            verbosity: config.verbosity,
+            width: terminal_width(),
+            ..Default::default()
+        }
+    }
+
+    fn show(&mut self, line: Line) {
+        self.shown += 1;
+
+        if self.verbosity > 0 {
+            let text = format!("{} {}", line.nodeid, line.outcome);
+            let length = line.nodeid.len() + 1 + line.outcome.chars().count();
+            println!("{}{}", text, self.progress(length));
+            if let Some(failure) = line.failure {
+                println!("{}", failure);
+            }
+            return;
+        }
+
+        if let Some(failure) = line.failure {
+            self.failures.push((line.nodeid, failure));
+        }
+        if self.verbosity < -1 {
+            return;
+        }
+
+        if self.verbosity == 0 && self.file.as_deref() != Some(line.file.as_str()) {
+            self.end_line(self.shown - 1);
+            print!("{} ", line.file);
+            self.column = line.file.len() + 1;
+            self.file = Some(line.file);
+        } else if self.column + 1 + PROGRESS_WIDTH > self.width {
+            self.end_line(self.shown - 1);
+        }
+        print!("{}", line.letter);
+        self.column += 1;
+        let _ = io::stdout().flush();
+    }
+
+    /// Pads the current line out to the progress column, and shows how far
+    /// through the tests the run is.
+    fn progress(&self, column: usize) -> String {
+        self.progress_of(column, self.shown)
+    }
+
+    fn progress_of(&self, column: usize, shown: usize) -> String {
+        let total = self.collected.max(shown).max(1);
+        let padding = self.width.saturating_sub(column + PROGRESS_WIDTH) + 1;
+        format!("{}[{:>3}%]", " ".repeat(padding), shown * 100 / total)
+    }
+
+    fn end_line(&mut self, shown: usize) {
+        if self.column > 0 {
+            println!("{}", self.progress_of(self.column, shown));
+            self.column = 0;
+        }
+    }
+}
+
+impl Reporter for Terminal {
+    fn collected(&mut self, test: &TestCase) -> Result<()> {
+        if !self.collect_only {
+            match &test.error {
+                Some(error) => {
+                    self.errors += 1;
+                    let nodeid = nodeid(test);
+                    let error = if self.verbosity > 1 {
+                        traceback(error).trim_end().to_string()
+                    } else {
+                        error.to_string()
+                    };
+                    self.outcomes.push(Outcome {
+                        char: 'E',
+                        file: test.file.clone(),
+                        nodeid: nodeid.clone(),
+                        message: first_line(&error),
+                    });
+                    self.collection_errors
+                        .push((nodeid, error.red().to_string()));
+                }
+                None if !test.deselected => self.collected += 1,
+                None => {}
+            }
+            return Ok(());
+        }
+        if test.deselected {
+            self.deselected += 1;
+            return Ok(());
+        }
+
+        match &test.error {
+            Some(error) => {
+                if test.name.is_empty() {
+                    println!("{} {}", "ERROR".red(), test.file.red());
+                } else {
+                    println!(
+                        "{} {}{}{}",
+                        "ERROR".red(),
+                        test.file.red(),
+                        "::".red(),
+                        test.name.red()
+                    );
+                }
+
+                println!("{}", error.to_string().red());
+                self.errors += 1
+            }
+            None => {
+                println!("{}::{}", test.file, test.name);
+                self.collected += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn collection_finish(&mut self) -> Result<()> {
+        self.collection_finished = true;
+        for line in std::mem::take(&mut self.held) {
+            self.show(line);
+        }
+
+        Ok(())
+    }
+
+    fn test_finish(&mut self, result: &TestCase) -> Result<()> {
+        if result.deselected {
+            self.deselected += 1;
+            return Ok(());
+        }
+
+        let nodeid = nodeid(result);
+        let (char, message) = match (&result.skipped, &result.xfail, &result.error) {
+            (Some(reason), _, _) => ('s', reason.clone()),
+            (None, Some(reason), _) if result.passed => ('X', reason.clone()),
+            (None, Some(reason), _) => ('x', reason.clone()),
+            (None, None, _) if result.passed => ('p', String::new()),
+            (None, None, error) => (
+                'f',
+                error
+                    .as_ref()
+                    .map(|e| first_line(&e.to_string()))
+                    .unwrap_or_default(),
+            ),
+        };
+        if self.durations.is_some() {
+            let durations = result.durations;
+            for (duration, phase) in [
+                (durations.setup, "setup"),
+                (durations.call, "call"),
+                (durations.teardown, "teardown"),
+            ] {
+                self.timings.push((duration, phase, nodeid.clone()));
+            }
+        }
+        self.outcomes.push(Outcome {
+            char,
+            file: result.file.clone(),
+            nodeid: nodeid.clone(),
+            message,
+        });
+        if char == 'p' && self.report_chars.contains('P') {
+            if let Some(output) = captured(result) {
+                self.passes.push((nodeid.clone(), output));
+            }
+        }
+
+        let (letter, outcome) = match (&result.skipped, &result.xfail) {
+            (Some(reason), _) => {
+                self.skipped += 1;
+                ("s".yellow(), with_reason("SKIPPED", reason).yellow())
+            }
+            (None, Some(reason)) if result.passed => {
+                self.xpassed += 1;
+                ("X".yellow(), with_reason("XPASS", reason).yellow())
+            }
+            (None, Some(reason)) => {
+                self.xfailed += 1;
+                ("x".yellow(), with_reason("XFAIL", reason).yellow())
+            }
+            (None, None) if result.passed => {
+                self.passed += 1;
+                (".".green(), "PASSED".green())
+            }
+            (None, None) => {
+                self.failed += 1;
+                ("F".red(), "FAILED".red())
+            }
+        };
+
+        let failure = match (&result.skipped, &result.xfail, result.passed, &result.error) {
+            (None, None, false, Some(error)) => {
+                let error = if self.verbosity > 1 {
+                    traceback(error).trim_end().to_string()
+                } else {
+                    error.to_string()
+                };
+                let mut failure = error.red().to_string();
+                if let Some(output) = captured(result) {
+                    failure.push('\n');
+                    failure.push_str(&output);
+                }
+                Some(failure)
+            }
+            _ => None,
+        };
+
+        let line = Line {
+            file: result.file.clone(),
+            nodeid,
+            letter,
+            outcome,
+            failure,
+        };
+        if self.collection_finished {
+            self.show(line);
+        } else {
+            self.held.push(line);
+        }
+
+        Ok(())
+    }
+
+    fn interrupted(&mut self, reason: &str) -> Result<()> {
+        self.interrupted = Some(reason.to_string());
+        Ok(())
+    }
+
+    fn session_finish(&mut self, duration: Duration, _exit_code: ExitCode) -> Result<()> {
+        let duration = duration.as_secs_f64();
+
+        if self.collect_only {
+            let mut summary = format!("{} tests collected", self.collected);
+            match self.errors {
+                0 => {}
+                1 => summary.push_str(&format!(", {} error", self.errors)),
+                _ => summary.push_str(&format!(", {} errors", self.errors)),
+            }
+            if self.deselected > 0 {
+                summary.push_str(&format!(", {} deselected", self.deselected));
+            }
+
+            self.print_interrupted();
+            println!("{} in {:.2}s", summary, duration);
+            return Ok(());
+        }
+
+        for line in std::mem::take(&mut self.held) {
+            self.show(line);
+        }
+        self.end_line(self.shown);
+
+        if !self.collection_errors.is_empty() {
+            println!("{}", separator('=', "ERRORS", self.width));
+            for (nodeid, error) in &self.collection_errors {
+                let title = format!("ERROR collecting {}", nodeid);
+                println!("{}", separator('_', &title, self.width));
+                println!("{}", error);
+            }
+        }
+        if !self.failures.is_empty() {
+            println!("{}", separator('=', "FAILURES", self.width));
+            for (nodeid, failure) in &self.failures {
+                println!("{}", separator('_', nodeid, self.width));
+                println!("{}", failure);
+            }
+        }
+        if !self.passes.is_empty() {
+            println!("{}", separator('=', "PASSES", self.width));
+            for (nodeid, output) in &self.passes {
+                println!("{}", separator('_', nodeid, self.width));
+                println!("{}", output);
+            }
+        }
+
+        if let Some(durations) = self.durations {
+            let title = match durations {
+                0 => "slowest durations".to_string(),
+                n => format!("slowest {} durations", n),
+            };
+            println!("{}", separator('=', &title, self.width));
+            for line in self.slowest(durations) {
+                println!("{}", line);
+            }
+        }
+
+        let lines = self.short_summary();
+        if !lines.is_empty() {
+            println!("{}", separator('=', "short test summary info", self.width));
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+
+        self.print_interrupted();
+
+        let mut summary = format!("{} passed, {} failed", self.passed, self.failed);
+        match self.errors {
+            0 => {}
+            1 => summary.push_str(", 1 error"),
+            _ => summary.push_str(&format!(", {} errors", self.errors)),
+        }
+        for (count, label) in [
+            (self.skipped, "skipped"),
+            (self.xfailed, "xfailed"),
+            (self.xpassed, "xpassed"),
+            (self.deselected, "deselected"),
+        ] {
+            if count > 0 {
+                summary.push_str(&format!(", {} {}", count, label));
+            }
+        }
+
+        println!("{} in {:.2}s", summary, duration);
+
+        if let Some(path) = &self.junitxml {
+            println!("generated xml file: {}", path.display());
+        }
+
+        Ok(())
+    }
+}
+
+impl Terminal {
+    fn print_interrupted(&self) {
+        if let Some(reason) = &self.interrupted {
+            println!("{}", separator('!', reason, self.width));
+        }
+    }
+
+    /// The `n` slowest phases of the tests, or all of them if `n` is 0,
+    /// leaving out those under `durations_min` unless `-vv` was given.
+    fn slowest(&self, n: usize) -> Vec<String> {
+        let mut timings: Vec<&(Duration, &str, String)> = self.timings.iter().collect();
+        timings.sort_by_key(|(duration, _, _)| std::cmp::Reverse(*duration));
+        if n > 0 {
+            timings.truncate(n);
+        }
+
+        let mut lines = vec![];
+        for (i, (duration, phase, nodeid)) in timings.iter().enumerate() {
+            if self.verbosity < 2 && *duration < self.durations_min {
+                lines.push(String::new());
+                lines.push(format!(
+                    "({} durations < {}s hidden.  Use -vv to show these durations.)",
+                    timings.len() - i,
+                    self.durations_min.as_secs_f64()
+                ));
+                break;
+            }
+            lines.push(format!(
+                "{:.2}s {:<8} {}",
+                duration.as_secs_f64(),
+                phase,
+                nodeid
+            ));
+        }
+
+        lines
+    }
+
+    /// The lines of the short test summary, grouped by outcome in the order
+    /// they were asked for with `-r`.
+    fn short_summary(&self) -> Vec<String> {
+        // `P` lists passed tests like `p`, as well as showing their output
+        let mut chars: Vec<char> = vec![];
+        for char in self.report_chars.replace('P', "p").chars() {
+            if !chars.contains(&char) {
+                chars.push(char);
+            }
+        }
+
+        let mut lines = vec![];
+        for char in chars {
+            let outcomes = self.outcomes.iter().filter(|o| o.char == char);
+
+            if char == 's' {
+                // skips are counted by file and reason, as pytest does by line
+                let mut skips: Vec<(&str, &str, usize)> = vec![];
+                for outcome in outcomes {
+                    match skips.iter_mut().find(|(file, reason, _)| {
+                        *file == outcome.file && *reason == outcome.message
+                    }) {
+                        Some((_, _, count)) => *count += 1,
+                        None => skips.push((&outcome.file, &outcome.message, 1)),
+                    }
+                }
+                for (file, reason, count) in skips {
+                    lines.push(format!(
+                        "{} [{}] {}: {}",
+                        "SKIPPED".yellow(),
+                        count,
+                        file,
+                        reason
+                    ));
+                }
+                continue;
+            }
+
+            let label = match char {
+                'f' => "FAILED".red(),
+                'E' => "ERROR".red(),
+                'x' => "XFAIL".yellow(),
+                'X' => "XPASS".yellow(),
+                _ => "PASSED".green(),
+            };
+            for outcome in outcomes {
+                let line = format!("{} {}", label, outcome.nodeid);
+                lines.push(match self.message(&line, &outcome.message) {
+                    Some(message) => format!("{} - {}", line, message),
+                    None => line,
+                });
+            }
+        }
+
+        lines
+    }
+
+    /// The message to follow a line of the short test summary, cut short to
+    /// fit the terminal unless `-vv` was given.
+    fn message<'a>(&self, line: &str, message: &'a str) -> Option<Cow<'a, str>> {
+        if message.is_empty() {
+            return None;
+        }
+        if self.verbosity > 1 {
+            return Some(Cow::Borrowed(message));
+        }
+
+        let available = self
+            .width
+            .checked_sub(console_width(line) + 3)
+            .filter(|available| *available > 3)?;
+        if message.chars().count() <= available {
+            Some(Cow::Borrowed(message))
+        } else {
+            let cut: String = message.chars().take(available - 3).collect();
+            Some(Cow::Owned(format!("{}...", cut)))
+        }
+    }
+}
+
+/// A test's node ID, or just its file if the file couldn't be collected.
+fn nodeid(test: &TestCase) -> String {
+    if test.name.is_empty() {
+        test.file.clone()
+    } else {
+        format!("{}::{}", test.file, test.name)
+    }
+}
+
+fn first_line(text: &str) -> String {
+    text.lines()
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .to_string()
+}
+
+/// The captured output of a test, if it printed anything.
+fn captured(result: &TestCase) -> Option<String> {
+    let sections: Vec<String> = [("stdout", &result.stdout), ("stderr", &result.stderr)]
+        .into_iter()
+        .filter(|(_, output)| !output.is_empty())
+        .map(|(stream, output)| format!("Captured {}:\n{}", stream, output.trim_end()))
+        .collect();
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n"))
+    }
+}
+
+/// The number of columns text takes up, without any color codes.
+fn console_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| *c == 'm');
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// The width of the terminal from `COLUMNS`, as Python finds it, or the
+/// terminal itself, defaulting to 80 columns when output isn't a terminal.
+fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(|| terminal_size::terminal_size().map(|(width, _)| width.0 as usize))
+        .unwrap_or(80)
+}
+
+/// A title centred in a line of `fill` characters, like pytest's section
+/// headers.
+fn separator(fill: char, title: &str, width: usize) -> String {
+    let sides = width.saturating_sub(title.len() + 2);
+    let left = fill.to_string().repeat(sides / 2);
+    let right = fill.to_string().repeat(sides - sides / 2);
+    format!("{} {} {}", left, title, right)
+}
+
+fn with_reason(outcome: &str, reason: &str) -> String {
+    if reason.is_empty() {
+        outcome.to_string()
+    } else {
+        format!("{} ({})", outcome, reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_separator() {
+        assert_eq!(separator('=', "FAILURES", 20), "===== FAILURES =====");
+        assert_eq!(separator('_', "test", 11), "__ test ___");
+        assert_eq!(separator('_', "a_long_title", 5), " a_long_title ");
+    }
+
+    #[test]
+    fn test_progress() {
+        let terminal = Terminal {
+            width: 20,
+            collected: 3,
+            shown: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(terminal.progress(5), "         [ 33%]");
+        assert_eq!(terminal.progress(30), " [ 33%]");
+    }
+
+    #[test]
+    fn test_slowest() {
+        let timing = |millis, phase, nodeid: &str| {
+            (Duration::from_millis(millis), phase, nodeid.to_string())
+        };
+        let mut terminal = Terminal {
+            durations_min: Duration::from_millis(5),
+            timings: vec![
+                timing(1, "setup", "test_a"),
+                timing(250, "call", "test_a"),
+                timing(0, "teardown", "test_a"),
+                timing(1200, "setup", "test_b"),
+                timing(30, "call", "test_b"),
+                timing(2, "teardown", "test_b"),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            terminal.slowest(2),
+            vec!["1.20s setup    test_b", "0.25s call     test_a"]
+        );
+        assert_eq!(
+            terminal.slowest(0),
+            vec![
+                "1.20s setup    test_b",
+                "0.25s call     test_a",
+                "0.03s call     test_b",
+                "",
+                "(3 durations < 0.005s hidden.  Use -vv to show these durations.)",
+            ]
+        );
+        terminal.verbosity = 2;
+        assert_eq!(terminal.slowest(0).len(), 6);
+    }
+
+    #[test]
+    fn test_message() {
+        let terminal = Terminal {
+            width: 30,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            terminal.message("FAILED test_a", "short").as_deref(),
+            Some("short")
+        );
+        assert_eq!(
+            terminal
+                .message("FAILED test_a", "a longer message")
+                .as_deref(),
+            Some("a longer me...")
+        );
+        assert_eq!(
+            terminal
+                .message(&"FAILED".red().to_string(), "a longer message")
+                .as_deref(),
+            Some("a longer message")
+        );
+        assert_eq!(
+            terminal.message("FAILED a_longer_test_name", "message"),
+            None
+        );
+        assert_eq!(terminal.message("FAILED test_a", ""), None);
+    }
+}
@@ -5,8 +5,9 @@ use std::env;
 use std::path::Path;
 
 use crate::structs::PyInfo;
+use crate::Config;
 
-pub fn setup<'a>(py: &'a Python, current_dir: &Path) -> Bound<'a, PyList> {
+pub fn setup<'a>(py: &'a Python, config: &Config) -> Bound<'a, PyList> {
     let syspath = py
         .import_bound("sys")
         .unwrap()
@@ -15,7 +16,11 @@ pub fn setup<'a>(py: &'a Python, current_dir: &Path) -> Bound<'a, PyList> {
         .downcast_into::<PyList>()
         .unwrap();
 
-    syspath.insert(0, current_dir).unwrap();
+    syspath.insert(0, &config.rootdir).unwrap();
+
+    for path in config.pythonpath.iter().rev() {
+        syspath.insert(0, path).unwrap();
+    }
 
     if let Ok(venv) = env::var("VIRTUAL_ENV") {
         let venv_path = Path::new(&venv);
@@ -30,9 +35,9 @@ pub fn setup<'a>(py: &'a Python, current_dir: &Path) -> Bound<'a, PyList> {
     syspath
 }
 
-pub fn get_info() -> Result<PyInfo> {
+pub fn get_info(config: &Config) -> Result<PyInfo> {
     Python::with_gil(|py| -> Result<PyInfo> {
-        let syspath = setup(&py, std::env::current_dir().unwrap().as_path());
+        let syspath = setup(&py, config);
 
         let sys = py.import_bound("sys")?;
 
@@ -1,14 +1,92 @@
 use pyo3::PyErr;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug)]
+use crate::expression::Expression;
+use crate::patterns::Patterns;
+
+#[derive(Debug, Clone, Default)]
 pub struct Config {
+    pub async_backend: String,
+    pub cache_clear: bool,
+    pub cache_dir: Option<PathBuf>,
+    /// Shared by every clone of the configuration, so each thread of a run
+    /// can see when it's been cancelled.
+    pub cancellation: Cancellation,
     pub collect_only: bool,
-    pub file_prefix: String,
+    /// Whether to color the output, or `None` to decide from the environment.
+    pub color: Option<bool>,
+    pub collect_threads: usize,
+    /// Run the tests that could be collected even if others couldn't be.
+    pub continue_on_collection_errors: bool,
+    pub deselect: Vec<String>,
+    pub doctest_globs: Vec<String>,
+    pub doctest_modules: bool,
+    pub doctest_optionflags: Vec<String>,
+    /// How many of the slowest setups, calls and teardowns to list, or
+    /// `Some(0)` for all of them, hiding those quicker than `durations_min`.
+    pub durations: Option<usize>,
+    pub durations_min: Duration,
+    pub dynamic_collection: bool,
     pub files: Vec<String>,
+    pub ignore_globs: Vec<String>,
     pub ignores: Vec<String>,
+    pub inifile: Option<PathBuf>,
     pub info: bool,
-    pub test_prefix: String,
-    pub verbose: bool,
+    pub junit_family: String,
+    pub junitxml: Option<PathBuf>,
+    pub keyword: Option<Expression>,
+    pub markers: Vec<String>,
+    pub markexpr: Option<Expression>,
+    /// How many tests can fail before the run is stopped, or 0 for no limit.
+    pub maxfail: usize,
+    pub node_ids: Vec<String>,
+    pub norecursedirs: Vec<String>,
+    pub python_classes: Patterns,
+    pub python_files: Patterns,
+    pub python_functions: Patterns,
+    pub pythonpath: Vec<PathBuf>,
+    /// The outcomes listed in the short test summary, as `-r` characters.
+    pub report_chars: String,
+    pub report_json: Option<PathBuf>,
+    pub report_jsonl: Option<String>,
+    pub respect_gitignore: bool,
+    pub rootdir: PathBuf,
+    pub strict_markers: bool,
+    /// How much to output, from `-qq` at -2 to `-vv` at 2.
+    pub verbosity: i8,
+}
+
+/// Counts failures across the threads of a run, cancelling it once
+/// `--maxfail` is reached so that collection stops along with execution.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation {
+    failures: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancellation {
+    /// Counts a failure, and returns whether it was the one that reached
+    /// `maxfail` and cancelled the run.
+    pub fn fail(&self, maxfail: usize) -> bool {
+        let failures = self.failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if maxfail > 0 && failures == maxfail {
+            self.cancel();
+            return true;
+        }
+        false
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
 }
 
 #[derive(Debug)]
@@ -18,6 +96,119 @@ pub struct TestCase {
     pub passed: bool,
     pub error: Option<PyErr>,
     pub parametrized: bool,
+    pub markers: Vec<Marker>,
+    pub deselected: bool,
+    /// Why the test was skipped, by a `skip` or `skipif` marker.
+    pub skipped: Option<String>,
+    /// Why the test was expected to fail, by an `xfail` marker. It xfailed if
+    /// it didn't pass, and xpassed if it did.
+    pub xfail: Option<String>,
+    /// Whether this is a doctest, named after the object its docstring belongs
+    /// to, or the text file it's in.
+    pub doctest: bool,
+    /// How long the test took to run, including its fixtures.
+    pub duration: Duration,
+    /// The same, split into setting the test up, calling it and tearing it
+    /// down again.
+    pub durations: Durations,
+    /// What the test wrote to `sys.stdout` and `sys.stderr` while it ran.
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl TestCase {
+    /// A collected test that hasn't run yet.
+    pub fn new(file: &str, name: &str) -> TestCase {
+        TestCase {
+            file: file.to_string(),
+            name: name.to_string(),
+            passed: false,
+            error: None,
+            parametrized: false,
+            markers: vec![],
+            deselected: false,
+            skipped: None,
+            xfail: None,
+            doctest: false,
+            duration: Duration::ZERO,
+            durations: Durations::default(),
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Whether the test failed, rather than passing, being skipped or failing
+    /// as expected.
+    pub fn failed(&self) -> bool {
+        !self.passed && !self.deselected && self.skipped.is_none() && self.xfail.is_none()
+    }
+
+    /// A copy of the test as it was collected, without its result or error.
+    pub fn without_result(&self) -> TestCase {
+        TestCase {
+            parametrized: self.parametrized,
+            markers: self.markers.clone(),
+            deselected: self.deselected,
+            doctest: self.doctest,
+            ..TestCase::new(&self.file, &self.name)
+        }
+    }
+}
+
+/// How long each phase of running a test took, where setup includes its
+/// fixtures and `setup_*` hooks, and teardown any scope it was the last in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Durations {
+    pub setup: Duration,
+    pub call: Duration,
+    pub teardown: Duration,
+}
+
+/// What happens to a test as it's run, sent on from the execution phase to be
+/// reported.
+#[derive(Debug)]
+pub enum Event {
+    /// The test was collected, and will be run unless it was deselected or
+    /// couldn't be collected, in which case it has an error.
+    Collected(TestCase),
+    /// Every test has been collected.
+    CollectionFinished,
+    /// The run was stopped before every test had run, and why.
+    Interrupted(String),
+    /// The run was cancelled after `--maxfail` failures, and why.
+    Stopped(String),
+    /// The test is about to run.
+    Started(TestCase),
+    /// The test finished, or was deselected, with its result.
+    Finished(TestCase),
+}
+
+/// How a run ended, as the exit code of the process, which is the same as
+/// pytest's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Every test passed.
+    Ok = 0,
+    /// Some tests failed, or couldn't be collected.
+    TestsFailed = 1,
+    /// The run was stopped early, by errors during collection or a
+    /// `KeyboardInterrupt`.
+    Interrupted = 2,
+    /// Something went wrong in rytest itself.
+    InternalError = 3,
+    /// The command line or configuration was invalid.
+    UsageError = 4,
+    /// There were no tests to run.
+    NoTestsCollected = 5,
+}
+
+/// A `@pytest.mark.<name>(...)` applied to a test, with the Python source of
+/// each of its arguments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    pub name: String,
+    pub args: Vec<String>,
+    pub kwargs: Vec<(String, String)>,
 }
 
 #[derive(Debug)]
@@ -26,3 +217,25 @@ pub struct PyInfo {
     pub version: String,
     pub path: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation() {
+        let cancellation = Cancellation::default();
+        let clone = cancellation.clone();
+
+        assert!(!cancellation.fail(2));
+        assert!(!cancellation.is_cancelled());
+        assert!(clone.fail(2));
+        assert!(cancellation.is_cancelled());
+        // only the failure that reached maxfail reports it
+        assert!(!cancellation.fail(2));
+
+        let unlimited = Cancellation::default();
+        assert!(!unlimited.fail(0));
+        assert!(!unlimited.is_cancelled());
+    }
+}
@@ -2,7 +2,10 @@ use insta_cmd::{assert_cmd_snapshot, get_cargo_bin};
 use std::process::Command;
 
 fn cli() -> Command {
-    Command::new(get_cargo_bin("rytest"))
+    let mut command = Command::new(get_cargo_bin("rytest"));
+    // progress is laid out to the terminal width, 80 columns when piped
+    command.env_remove("COLUMNS");
+    command
 }
 
 fn setup() -> insta::Settings {
@@ -25,21 +28,63 @@ fn help() {
                      Usage: rytest [OPTIONS] [FILE]...
 
                      Arguments:
-                       [FILE]...  Input file(s) [default: .]
+                       [FILE]...  Input file(s), folder(s) or node ID(s) such as tests/test_file.py::test_name [default:
+                                  .]
 
                      Options:
-                           --collect-only               only collect tests, don't run them
-                       -f, --file-prefix <file_prefix>  The prefix to search for to indicate a file contains tests
-                                                        [default: test_]
-                       -i, --ignore <ignore>            Ignore file(s) and folders. Can be used multiple times [default:
-                                                        .venv]
-                           --info                       Print information about rytest and the python environment it is
-                                                        running in.
-                       -p, --test-prefix <test_prefix>  The prefix to search for to indicate a function is a test
-                                                        [default: test_]
-                       -v, --verbose                    Verbose output
-                       -h, --help                       Print help
-                       -V, --version                    Print version
+                           --async-backend <NAME>           Event loop to run async tests and fixtures on [default:
+                                                            asyncio] [possible values: asyncio, trio, uvloop]
+                           --cache-clear                    Remove the collection cache before running
+                           --collect-only                   only collect tests, don't run them
+                           --collect-threads <N>            Number of threads used to parse test files, defaults to the
+                                                            number of CPUs
+                           --color <WHEN>                   Color the output [default: auto] [possible values: yes, no,
+                                                            auto]
+                           --continue-on-collection-errors  Run the tests that could be collected even if others couldn't
+                                                            be
+                           --deselect <NODEID>              Deselect the test(s) with the given node ID prefix. Can be
+                                                            used multiple times
+                           --doctest-glob <GLOB>            Collect doctests from text files matching the glob pattern
+                                                            [default: test*.txt]
+                           --doctest-modules                Collect doctests from the docstrings of all python modules
+                           --durations <N>                  List the N slowest setup, call and teardown durations, or all
+                                                            of them if 0
+                           --durations-min <SECONDS>        Leave durations shorter than SECONDS out of --durations,
+                                                            unless -vv is given [default: 0.005]
+                           --dynamic-collection             Also import test modules to find tests created at runtime
+                       -x, --exitfirst                      Stop after the first failure, short for --maxfail=1
+                       -f, --file-prefix <PREFIX>           Only search files starting with this prefix for tests, short
+                                                            for python_files = PREFIX*.py
+                       -i, --ignore <ignore>                Ignore file(s) and folders. Can be used multiple times
+                                                            [default: .venv]
+                           --ignore-glob <GLOB>             Ignore file(s) and folders matching the glob pattern. Can be
+                                                            used multiple times
+                           --info                           Print information about rytest and the python environment it
+                                                            is running in.
+                           --junitxml <PATH>                Write a JUnit XML report of the results to PATH
+                       -k <EXPRESSION>                      Only run tests whose names, classes, modules or markers match
+                                                            the given expression, e.g. -k 'http and not slow'
+                       -m <MARKEXPR>                        Only run tests matching the given marker expression, e.g. -m
+                                                            'slow and not db'
+                           --maxfail <N>                    Stop after N failures
+                       -q, --quiet...                       Less output, with -qq for only failures and the summary
+                       -r <CHARS>                           Show a short summary of the tests with the given outcomes:
+                                                            (f)ailed, (E)rror, (s)kipped, (x)failed, (X)passed, (p)assed,
+                                                            (P)assed with output, (w)arnings (ignored), (a)ll except
+                                                            passed, (A)ll or (N)one [default: fE]
+                           --report-json <PATH>             Write a JSON report of the results to PATH
+                           --report-jsonl <PATH>            Stream events as JSON Lines to PATH, or to stdout instead of
+                                                            text if -
+                           --respect-gitignore              Skip files and folders excluded by .gitignore and .ignore
+                                                            files
+                           --rootdir <ROOTDIR>              Define the root directory for tests, which node IDs are
+                                                            relative to
+                           --strict-markers                 Error on markers that are not registered in configuration
+                       -p, --test-prefix <PREFIX>           Only collect functions starting with this prefix as tests,
+                                                            short for python_functions = PREFIX*
+                       -v, --verbose...                     A line per test, with -vv for full tracebacks
+                       -h, --help                           Print help
+                       -V, --version                        Print version
 
                      ----- stderr -----
                      "###));
@@ -51,25 +96,73 @@ fn collect_errors() {
 
     settings.bind(|| {
         assert_cmd_snapshot!(cli().arg("tests").arg("--collect-only"), @r###"
-        success: true
-        exit_code: 0
+        success: false
+        exit_code: 2
         ----- stdout -----
+        tests/input/asyncio/test_async.py::test_async_passes
+        tests/input/asyncio/test_async.py::test_async_fails
+        tests/input/asyncio/test_async.py::test_async_fixture
+        tests/input/asyncio/test_async.py::test_async_generator_fixture
+        tests/input/asyncio/test_async.py::test_async_generator_fixture_torn_down
+        tests/input/asyncio/test_async.py::test_unawaited_coroutine
+        tests/input/asyncio/test_async.py::TestAsyncMethods::test_method
+        tests/input/asyncio/test_async.py::test_module_fixture
+        tests/input/asyncio/test_async.py::TestScopedFixtures::test_first
+        tests/input/asyncio/test_async.py::TestScopedFixtures::test_second
+        tests/input/asyncio/test_async.py::test_class_fixture_torn_down
         ERROR tests/input/bad/test_other_error.py
         SyntaxError:  Error parsing expected an indented block at byte offset 30
         tests/input/bad/test_other_file.py::test_function_passes
         tests/input/bad/test_other_file.py::test_function_fails
+        tests/input/capture/test_capture.py::test_prints
+        tests/input/capture/test_capture.py::test_prints_and_fails
+        tests/input/capture/test_capture.py::test_skipped
+        tests/input/capture/test_capture.py::test_xfailed
         tests/input/classes/test_classes.py::SomeTest::test_something
         tests/input/classes/test_classes.py::SomeTest::test_something_else
         tests/input/classes/test_classes.py::SomeTest::test_assert_failure
+        tests/input/durations/test_durations.py::test_slow
+        tests/input/durations/test_durations.py::test_quick
+        tests/input/dynamic/test_conditional.py::test_on_python3
+        tests/input/dynamic/test_conditional.py::test_after_try_import
         tests/input/folder/test_another_file.py::test_another_function
         tests/input/folder/test_another_file.py::test_function_with_decorator
         tests/input/good/test_success.py::test_success
         tests/input/good/test_success.py::test_more_success
         tests/input/good/test_success.py::test_using_fixture
+        tests/input/interrupt/test_interrupt.py::test_before
+        tests/input/interrupt/test_interrupt.py::test_interrupts
+        tests/input/interrupt/test_interrupt.py::test_after
+        tests/input/markers/test_markers.py::test_slow
+        tests/input/markers/test_markers.py::test_slow_db
+        tests/input/markers/test_markers.py::test_unmarked
+        tests/input/markers/test_markers.py::TestMarkedClass::test_in_class
+        tests/input/markers/test_markers.py::TestMarkedClass::test_slow_in_class
+        tests/input/marks/test_class_marks.py::test_function_usefixtures
+        tests/input/marks/test_class_marks.py::TestUsefixtures::test_method
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_less[1-2]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_less[3-4]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_nested[1-2-5]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_nested[3-4-5]
+        tests/input/marks/test_module_skip.py::test_first
+        tests/input/marks/test_module_skip.py::test_second
+        tests/input/marks/test_pytestmark.py::test_uses_module_fixture
+        tests/input/marks/test_pytestmark.py::test_skipif_string
+        tests/input/marks/test_pytestmark.py::test_skipif_false
+        tests/input/marks/test_pytestmark.py::test_xfail
+        tests/input/marks/test_pytestmark.py::test_xpass
+        tests/input/marks/test_pytestmark.py::test_xpass_strict
+        tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception
+        tests/input/marks/test_pytestmark.py::TestSkipped::test_one
+        tests/input/marks/test_pytestmark.py::TestSkipped::test_two
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[1]
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[2]
         ERROR tests/input/test_bad_file.py
         SyntaxError:  Error parsing expected an indented block at byte offset 31
         tests/input/test_file.py::test_function_passes
         tests/input/test_file.py::test_function_fails
+        tests/input/test_file.py::test_function_skipped
+        tests/input/test_file.py::test_function_skipped_reason
         tests/input/test_file.py::test_parameterized[1]
         tests/input/test_file.py::test_parameterized[2]
         tests/input/test_file.py::test_parameterized[3]
@@ -87,7 +180,13 @@ fn collect_errors() {
         tests/input/test_file.py::test_parameterized_functions[int]
         tests/input/test_file.py::test_parameterized_functions[float]
         tests/input/test_fixtures.py::test_fixture
-        29 tests collected, 2 errors in <TIME>s
+        tests/input/xunit/test_xunit.py::test_module_setup
+        tests/input/xunit/test_xunit.py::test_function_teardown
+        tests/input/xunit/test_xunit.py::TestXunitClass::test_class_setup
+        tests/input/xunit/test_xunit.py::TestXunitClass::test_method_teardown
+        tests/input/xunit/test_xunit.py::UnittestClass::test_unittest_setup
+        !!!!!!!!!!!!!!!!!!! Interrupted: 2 errors during collection !!!!!!!!!!!!!!!!!!!!
+        82 tests collected, 2 errors in <TIME>s
 
         ----- stderr -----
         "###)
@@ -100,13 +199,14 @@ fn collect_error() {
 
     settings.bind(|| {
         assert_cmd_snapshot!(cli().arg("tests/input/bad").arg("--collect-only"), @r###"
-        success: true
-        exit_code: 0
+        success: false
+        exit_code: 2
         ----- stdout -----
         ERROR tests/input/bad/test_other_error.py
         SyntaxError:  Error parsing expected an indented block at byte offset 30
         tests/input/bad/test_other_file.py::test_function_passes
         tests/input/bad/test_other_file.py::test_function_fails
+        !!!!!!!!!!!!!!!!!!!! Interrupted: 1 error during collection !!!!!!!!!!!!!!!!!!!!
         2 tests collected, 1 error in <TIME>s
 
         ----- stderr -----
@@ -120,21 +220,69 @@ fn collect_ignore_folder() {
 
     settings.bind(|| {
         assert_cmd_snapshot!(cli().arg("tests/input").arg("--collect-only").arg("--ignore").arg("tests/input/bad"), @r###"
-        success: true
-        exit_code: 0
+        success: false
+        exit_code: 2
         ----- stdout -----
+        tests/input/asyncio/test_async.py::test_async_passes
+        tests/input/asyncio/test_async.py::test_async_fails
+        tests/input/asyncio/test_async.py::test_async_fixture
+        tests/input/asyncio/test_async.py::test_async_generator_fixture
+        tests/input/asyncio/test_async.py::test_async_generator_fixture_torn_down
+        tests/input/asyncio/test_async.py::test_unawaited_coroutine
+        tests/input/asyncio/test_async.py::TestAsyncMethods::test_method
+        tests/input/asyncio/test_async.py::test_module_fixture
+        tests/input/asyncio/test_async.py::TestScopedFixtures::test_first
+        tests/input/asyncio/test_async.py::TestScopedFixtures::test_second
+        tests/input/asyncio/test_async.py::test_class_fixture_torn_down
+        tests/input/capture/test_capture.py::test_prints
+        tests/input/capture/test_capture.py::test_prints_and_fails
+        tests/input/capture/test_capture.py::test_skipped
+        tests/input/capture/test_capture.py::test_xfailed
         tests/input/classes/test_classes.py::SomeTest::test_something
         tests/input/classes/test_classes.py::SomeTest::test_something_else
         tests/input/classes/test_classes.py::SomeTest::test_assert_failure
+        tests/input/durations/test_durations.py::test_slow
+        tests/input/durations/test_durations.py::test_quick
+        tests/input/dynamic/test_conditional.py::test_on_python3
+        tests/input/dynamic/test_conditional.py::test_after_try_import
         tests/input/folder/test_another_file.py::test_another_function
         tests/input/folder/test_another_file.py::test_function_with_decorator
         tests/input/good/test_success.py::test_success
         tests/input/good/test_success.py::test_more_success
         tests/input/good/test_success.py::test_using_fixture
+        tests/input/interrupt/test_interrupt.py::test_before
+        tests/input/interrupt/test_interrupt.py::test_interrupts
+        tests/input/interrupt/test_interrupt.py::test_after
+        tests/input/markers/test_markers.py::test_slow
+        tests/input/markers/test_markers.py::test_slow_db
+        tests/input/markers/test_markers.py::test_unmarked
+        tests/input/markers/test_markers.py::TestMarkedClass::test_in_class
+        tests/input/markers/test_markers.py::TestMarkedClass::test_slow_in_class
+        tests/input/marks/test_class_marks.py::test_function_usefixtures
+        tests/input/marks/test_class_marks.py::TestUsefixtures::test_method
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_less[1-2]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_less[3-4]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_nested[1-2-5]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_nested[3-4-5]
+        tests/input/marks/test_module_skip.py::test_first
+        tests/input/marks/test_module_skip.py::test_second
+        tests/input/marks/test_pytestmark.py::test_uses_module_fixture
+        tests/input/marks/test_pytestmark.py::test_skipif_string
+        tests/input/marks/test_pytestmark.py::test_skipif_false
+        tests/input/marks/test_pytestmark.py::test_xfail
+        tests/input/marks/test_pytestmark.py::test_xpass
+        tests/input/marks/test_pytestmark.py::test_xpass_strict
+        tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception
+        tests/input/marks/test_pytestmark.py::TestSkipped::test_one
+        tests/input/marks/test_pytestmark.py::TestSkipped::test_two
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[1]
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[2]
         ERROR tests/input/test_bad_file.py
         SyntaxError:  Error parsing expected an indented block at byte offset 31
         tests/input/test_file.py::test_function_passes
         tests/input/test_file.py::test_function_fails
+        tests/input/test_file.py::test_function_skipped
+        tests/input/test_file.py::test_function_skipped_reason
         tests/input/test_file.py::test_parameterized[1]
         tests/input/test_file.py::test_parameterized[2]
         tests/input/test_file.py::test_parameterized[3]
@@ -152,7 +300,13 @@ fn collect_ignore_folder() {
         tests/input/test_file.py::test_parameterized_functions[int]
         tests/input/test_file.py::test_parameterized_functions[float]
         tests/input/test_fixtures.py::test_fixture
-        27 tests collected, 1 error in <TIME>s
+        tests/input/xunit/test_xunit.py::test_module_setup
+        tests/input/xunit/test_xunit.py::test_function_teardown
+        tests/input/xunit/test_xunit.py::TestXunitClass::test_class_setup
+        tests/input/xunit/test_xunit.py::TestXunitClass::test_method_teardown
+        tests/input/xunit/test_xunit.py::UnittestClass::test_unittest_setup
+        !!!!!!!!!!!!!!!!!!!! Interrupted: 1 error during collection !!!!!!!!!!!!!!!!!!!!
+        80 tests collected, 1 error in <TIME>s
 
         ----- stderr -----
         "###)
@@ -187,9 +341,9 @@ fn test() {
         success: true
         exit_code: 0
         ----- stdout -----
-        tests/input/good/test_success.py::test_success - PASSED
-        tests/input/good/test_success.py::test_more_success - PASSED
-        tests/input/good/test_success.py::test_using_fixture - PASSED
+        tests/input/good/test_success.py::test_success PASSED                     [ 33%]
+        tests/input/good/test_success.py::test_more_success PASSED                [ 66%]
+        tests/input/good/test_success.py::test_using_fixture PASSED               [100%]
         3 passed, 0 failed in <TIME>s
 
         ----- stderr -----
@@ -203,16 +357,1270 @@ fn test_relative_imports() {
 
     settings.bind(|| {
         assert_cmd_snapshot!(cli().arg("tests/input/folder").arg("-v"), @r###"
-        success: true
-        exit_code: 0
+        success: false
+        exit_code: 1
         ----- stdout -----
-        tests/input/folder/test_another_file.py::test_another_function - FAILED
+        tests/input/folder/test_another_file.py::test_another_function FAILED     [ 50%]
         ImportError: attempted relative import with no known parent package
-        tests/input/folder/test_another_file.py::test_function_with_decorator - FAILED
+        tests/input/folder/test_another_file.py::test_function_with_decorator FAILED [100%]
         ImportError: attempted relative import with no known parent package
+        =========================== short test summary info ============================
+        FAILED tests/input/folder/test_another_file.py::test_another_function - Impor...
+        FAILED tests/input/folder/test_another_file.py::test_function_with_decorator
         0 passed, 2 failed in <TIME>s
 
         ----- stderr -----
         "###)
     });
 }
+
+#[test]
+fn test_xunit() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/xunit").arg("-v"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/xunit/test_xunit.py::test_module_setup PASSED                 [ 20%]
+        tests/input/xunit/test_xunit.py::test_function_teardown PASSED            [ 40%]
+        tests/input/xunit/test_xunit.py::TestXunitClass::test_class_setup PASSED  [ 60%]
+        tests/input/xunit/test_xunit.py::TestXunitClass::test_method_teardown PASSED [ 80%]
+        tests/input/xunit/test_xunit.py::UnittestClass::test_unittest_setup PASSED [100%]
+        5 passed, 0 failed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_marks() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/marks").arg("-v"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        tests/input/marks/test_class_marks.py::test_function_usefixtures PASSED   [  5%]
+        tests/input/marks/test_class_marks.py::TestUsefixtures::test_method PASSED [ 10%]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_less[1-2] PASSED [ 15%]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_less[3-4] PASSED [ 21%]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_nested[1-2-5] PASSED [ 26%]
+        tests/input/marks/test_class_marks.py::TestParametrizedClass::test_nested[3-4-5] PASSED [ 31%]
+        tests/input/marks/test_module_skip.py::test_first SKIPPED (integration environment unavailable) [ 36%]
+        tests/input/marks/test_module_skip.py::test_second SKIPPED (integration environment unavailable) [ 42%]
+        tests/input/marks/test_pytestmark.py::test_uses_module_fixture PASSED     [ 47%]
+        tests/input/marks/test_pytestmark.py::test_skipif_string SKIPPED (never on this platform) [ 52%]
+        tests/input/marks/test_pytestmark.py::test_skipif_false PASSED            [ 57%]
+        tests/input/marks/test_pytestmark.py::test_xfail XFAIL (known bug)        [ 63%]
+        tests/input/marks/test_pytestmark.py::test_xpass XPASS                    [ 68%]
+        tests/input/marks/test_pytestmark.py::test_xpass_strict FAILED            [ 73%]
+        AssertionError: [XPASS(strict)]
+        tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception FAILED   [ 78%]
+        ValueError: not a KeyError
+        tests/input/marks/test_pytestmark.py::TestSkipped::test_one SKIPPED (whole class) [ 84%]
+        tests/input/marks/test_pytestmark.py::TestSkipped::test_two SKIPPED (whole class) [ 89%]
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[1] PASSED [ 94%]
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[2] PASSED [100%]
+        =========================== short test summary info ============================
+        FAILED tests/input/marks/test_pytestmark.py::test_xpass_strict - AssertionErr...
+        FAILED tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception - Val...
+        10 passed, 2 failed, 5 skipped, 1 xfailed, 1 xpassed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_conditional() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/dynamic").arg("--collect-only"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/dynamic/test_conditional.py::test_on_python3
+        tests/input/dynamic/test_conditional.py::test_after_try_import
+        2 tests collected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_dynamic_collection() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/dynamic").arg("--dynamic-collection").arg("-v"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/dynamic/test_conditional.py::test_on_python3 PASSED           [ 16%]
+        tests/input/dynamic/test_conditional.py::test_after_try_import PASSED     [ 33%]
+        tests/input/dynamic/test_conditional.py::test_generated_0 PASSED          [ 50%]
+        tests/input/dynamic/test_conditional.py::test_generated_1 PASSED          [ 66%]
+        tests/input/dynamic/test_conditional.py::TestGeneratedMethods::test_method_0 PASSED [ 83%]
+        tests/input/dynamic/test_conditional.py::TestGeneratedMethods::test_method_1 PASSED [100%]
+        6 passed, 0 failed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_async() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/asyncio").arg("-v"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        tests/input/asyncio/test_async.py::test_async_passes PASSED               [  9%]
+        tests/input/asyncio/test_async.py::test_async_fails FAILED                [ 18%]
+        AssertionError: 
+        tests/input/asyncio/test_async.py::test_async_fixture PASSED              [ 27%]
+        tests/input/asyncio/test_async.py::test_async_generator_fixture PASSED    [ 36%]
+        tests/input/asyncio/test_async.py::test_async_generator_fixture_torn_down PASSED [ 45%]
+        tests/input/asyncio/test_async.py::test_unawaited_coroutine FAILED        [ 54%]
+        RuntimeError: coroutine 'helper' was never awaited
+        tests/input/asyncio/test_async.py::TestAsyncMethods::test_method PASSED   [ 63%]
+        tests/input/asyncio/test_async.py::test_module_fixture PASSED             [ 72%]
+        tests/input/asyncio/test_async.py::TestScopedFixtures::test_first PASSED  [ 81%]
+        tests/input/asyncio/test_async.py::TestScopedFixtures::test_second PASSED [ 90%]
+        tests/input/asyncio/test_async.py::test_class_fixture_torn_down PASSED    [100%]
+        =========================== short test summary info ============================
+        FAILED tests/input/asyncio/test_async.py::test_async_fails - AssertionError:
+        FAILED tests/input/asyncio/test_async.py::test_unawaited_coroutine - RuntimeE...
+        9 passed, 2 failed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_doctests() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/doctests")
+            .arg("--doctest-modules")
+            .arg("--doctest-glob")
+            .arg("*.md")
+            .arg("-v"), @r###"
+            success: false
+            exit_code: 1
+            ----- stdout -----
+            tests/input/doctests/guide.md::guide.md FAILED                            [ 16%]
+            AssertionError: **********************************************************************
+            File "tests/input/doctests/guide.md", line 14, in guide.md
+            Failed example:
+                print("hello world")
+            Expected:
+                hello, world
+            Got:
+                hello world
+            tests/input/doctests/pkg/calc.py::pkg.calc PASSED                         [ 33%]
+            tests/input/doctests/pkg/calc.py::pkg.calc.Calculator PASSED              [ 50%]
+            tests/input/doctests/pkg/calc.py::pkg.calc.Calculator.subtract PASSED     [ 66%]
+            tests/input/doctests/pkg/calc.py::pkg.calc.add PASSED                     [ 83%]
+            tests/input/doctests/pkg/calc.py::pkg.calc.numbers PASSED                 [100%]
+            =========================== short test summary info ============================
+            FAILED tests/input/doctests/guide.md::guide.md - AssertionError: ************...
+            5 passed, 1 failed in <TIME>s
+
+            ----- stderr -----
+            "###)
+    });
+}
+
+#[test]
+fn collect_doctests_skips_setup_py() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/doctests/project")
+            .arg("--doctest-modules")
+            .arg("--collect-only"), @r###"
+            success: false
+            exit_code: 5
+            ----- stdout -----
+            0 tests collected in <TIME>s
+
+            ----- stderr -----
+            "###);
+    });
+}
+
+#[test]
+fn collect_doctests_not_enabled() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/doctests").arg("--collect-only"), @r###"
+        success: false
+        exit_code: 5
+        ----- stdout -----
+        0 tests collected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_markexpr_pytestmark() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/marks").arg("--collect-only").arg("-m").arg("slow and not skip"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/marks/test_pytestmark.py::test_uses_module_fixture
+        tests/input/marks/test_pytestmark.py::test_skipif_string
+        tests/input/marks/test_pytestmark.py::test_skipif_false
+        tests/input/marks/test_pytestmark.py::test_xfail
+        tests/input/marks/test_pytestmark.py::test_xpass
+        tests/input/marks/test_pytestmark.py::test_xpass_strict
+        tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[1]
+        tests/input/marks/test_pytestmark.py::TestParametrized::test_value[2]
+        9 tests collected, 10 deselected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_markexpr() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/markers").arg("--collect-only").arg("-m").arg("slow and not db"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/markers/test_markers.py::test_slow
+        1 tests collected, 4 deselected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_markexpr_class() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/markers").arg("--collect-only").arg("-m").arg("db"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/markers/test_markers.py::test_slow_db
+        tests/input/markers/test_markers.py::TestMarkedClass::test_in_class
+        tests/input/markers/test_markers.py::TestMarkedClass::test_slow_in_class
+        3 tests collected, 2 deselected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_markexpr_invalid() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/markers").arg("--collect-only").arg("-m").arg("slow and"), @r###"
+        success: false
+        exit_code: 4
+        ----- stdout -----
+
+        ----- stderr -----
+        Wrong expression passed to '-m': expected identifier, found end of expression
+        "###)
+    });
+}
+
+#[test]
+fn collect_strict_markers() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/markers").arg("--collect-only").arg("--strict-markers"), @r###"
+        success: false
+        exit_code: 2
+        ----- stdout -----
+        ERROR tests/input/markers/test_markers.py::test_slow
+        ValueError: 'slow' not found in `markers` configuration option
+        ERROR tests/input/markers/test_markers.py::test_slow_db
+        ValueError: 'db' not found in `markers` configuration option
+        ERROR tests/input/markers/test_markers.py::test_unmarked
+        ValueError: 'integration' not found in `markers` configuration option
+        ERROR tests/input/markers/test_markers.py::TestMarkedClass::test_in_class
+        ValueError: 'db' not found in `markers` configuration option
+        ERROR tests/input/markers/test_markers.py::TestMarkedClass::test_slow_in_class
+        ValueError: 'slow' not found in `markers` configuration option
+        !!!!!!!!!!!!!!!!!!! Interrupted: 5 errors during collection !!!!!!!!!!!!!!!!!!!!
+        0 tests collected, 5 errors in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_keyword() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input").arg("--collect-only").arg("-k").arg("parameterized and not (tuple or NESTED) or markedclass"), @r###"
+        success: false
+        exit_code: 2
+        ----- stdout -----
+        ERROR tests/input/bad/test_other_error.py
+        SyntaxError:  Error parsing expected an indented block at byte offset 30
+        tests/input/markers/test_markers.py::TestMarkedClass::test_in_class
+        tests/input/markers/test_markers.py::TestMarkedClass::test_slow_in_class
+        ERROR tests/input/test_bad_file.py
+        SyntaxError:  Error parsing expected an indented block at byte offset 31
+        tests/input/test_file.py::test_parameterized[1]
+        tests/input/test_file.py::test_parameterized[2]
+        tests/input/test_file.py::test_parameterized[3]
+        tests/input/test_file.py::test_parameterized_expression[0]
+        tests/input/test_file.py::test_parameterized_expression[1]
+        tests/input/test_file.py::test_parameterized_expression[2]
+        tests/input/test_file.py::test_parameterized_functions[round]
+        tests/input/test_file.py::test_parameterized_functions[sum]
+        tests/input/test_file.py::test_parameterized_functions[int]
+        tests/input/test_file.py::test_parameterized_functions[float]
+        !!!!!!!!!!!!!!!!!!! Interrupted: 2 errors during collection !!!!!!!!!!!!!!!!!!!!
+        12 tests collected, 2 errors, 70 deselected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_keyword_parametrize_id() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/test_file.py").arg("--collect-only").arg("-k").arg("test_parameterized[2] or round"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/test_file.py::test_parameterized[2]
+        tests/input/test_file.py::test_parameterized_functions[round]
+        2 tests collected, 18 deselected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_keyword() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/good").arg("-v").arg("-k").arg("more"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/good/test_success.py::test_more_success PASSED                [100%]
+        1 passed, 0 failed, 2 deselected in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_node_ids() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/test_file.py::test_function_passes")
+            .arg("tests/input/classes/test_classes.py::SomeTest")
+            .arg("./tests/input/test_file.py::test_parameterized_tuple[3-4]")
+            .arg("--collect-only"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            tests/input/test_file.py::test_function_passes
+            tests/input/test_file.py::test_parameterized_tuple[3-4]
+            tests/input/classes/test_classes.py::SomeTest::test_something
+            tests/input/classes/test_classes.py::SomeTest::test_something_else
+            tests/input/classes/test_classes.py::SomeTest::test_assert_failure
+            5 tests collected in <TIME>s
+
+            ----- stderr -----
+            "###)
+    });
+}
+
+#[test]
+fn collect_node_id_and_whole_file() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/good/test_success.py")
+            .arg("tests/input/good/test_success.py::test_success")
+            .arg("--collect-only"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            tests/input/good/test_success.py::test_success
+            tests/input/good/test_success.py::test_more_success
+            tests/input/good/test_success.py::test_using_fixture
+            3 tests collected in <TIME>s
+
+            ----- stderr -----
+            "###);
+    });
+}
+
+#[test]
+fn collect_node_id_not_found() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/test_file.py::test_missing").arg("--collect-only"), @r###"
+        success: false
+        exit_code: 2
+        ----- stdout -----
+        ERROR tests/input/test_file.py::test_missing
+        ValueError: not found: tests/input/test_file.py::test_missing
+        !!!!!!!!!!!!!!!!!!!! Interrupted: 1 error during collection !!!!!!!!!!!!!!!!!!!!
+        0 tests collected, 1 error in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn collect_deselect() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/test_file.py")
+            .arg("--deselect")
+            .arg("tests/input/test_file.py::test_parameterized")
+            .arg("--deselect")
+            .arg("tests/input/test_file.py::test_function_fails")
+            .arg("--collect-only"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            tests/input/test_file.py::test_function_passes
+            tests/input/test_file.py::test_function_skipped
+            tests/input/test_file.py::test_function_skipped_reason
+            tests/input/test_file.py::test_parameterized_tuple[1-2]
+            tests/input/test_file.py::test_parameterized_tuple[3-4]
+            tests/input/test_file.py::test_parameterized_nested[a-1-2]
+            tests/input/test_file.py::test_parameterized_nested[a-3-4]
+            tests/input/test_file.py::test_parameterized_nested[c-1-2]
+            tests/input/test_file.py::test_parameterized_nested[c-3-4]
+            tests/input/test_file.py::test_parameterized_expression[0]
+            tests/input/test_file.py::test_parameterized_expression[1]
+            tests/input/test_file.py::test_parameterized_expression[2]
+            tests/input/test_file.py::test_parameterized_functions[round]
+            tests/input/test_file.py::test_parameterized_functions[sum]
+            tests/input/test_file.py::test_parameterized_functions[int]
+            tests/input/test_file.py::test_parameterized_functions[float]
+            16 tests collected, 4 deselected in <TIME>s
+
+            ----- stderr -----
+            "###)
+    });
+}
+
+#[test]
+fn test_node_id() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/good/test_success.py::test_more_success").arg("-v"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/good/test_success.py::test_more_success PASSED                [100%]
+        1 passed, 0 failed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_config_file() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().current_dir("tests/input/config").arg("-v"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        checks/check_things.py::check_pythonpath PASSED                           [ 20%]
+        checks/check_things.py::check_marker PASSED                               [ 40%]
+        checks/check_things.py::CheckClass::check_method PASSED                   [ 60%]
+        checks/more_checks.py::verify_glob_patterns PASSED                        [ 80%]
+        checks/more_checks.py::check_glob_patterns PASSED                         [100%]
+        5 passed, 0 failed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn config_addopts_overridden() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .current_dir("tests/input/addopts")
+            .arg("-m")
+            .arg("slow")
+            .arg("--collect-only"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            check_addopts.py::check_slow
+            1 tests collected, 1 deselected in <TIME>s
+
+            ----- stderr -----
+            "###);
+    });
+}
+
+#[test]
+fn collect_config_file_cli_precedence() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .current_dir("tests/input/config")
+            .arg("checks/skipped")
+            .arg("--test-prefix")
+            .arg("check_in")
+            .arg("--collect-only"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            checks/skipped/check_skipped.py::check_in_norecursedir
+            1 tests collected in <TIME>s
+
+            ----- stderr -----
+            "###)
+    });
+}
+
+#[test]
+fn collect_from_rootdir_subdirectory() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .current_dir("tests/input/config/checks")
+            .arg("check_things.py::check_marker")
+            .arg("--collect-only"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            checks/check_things.py::check_marker
+            1 tests collected in <TIME>s
+
+            ----- stderr -----
+            "###)
+    });
+}
+
+#[test]
+fn collect_rootdir_argument() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/folder")
+            .arg("--rootdir")
+            .arg("tests/input")
+            .arg("--collect-only"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            folder/test_another_file.py::test_another_function
+            folder/test_another_file.py::test_function_with_decorator
+            2 tests collected in <TIME>s
+
+            ----- stderr -----
+            "###)
+    });
+}
+
+#[test]
+fn test_junitxml() {
+    let mut settings = setup();
+    settings.add_filter(r#"time="[0-9.]+""#, r#"time="<TIME>""#);
+    settings.add_filter(r#"File &quot;.*?&quot;"#, "File &quot;<FILE>&quot;");
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("reports").join("junit.xml");
+    settings.add_filter(&report.display().to_string(), "<REPORT>");
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/capture")
+            .arg("-v")
+            .arg("--junitxml")
+            .arg(&report), @r###"
+            success: false
+            exit_code: 1
+            ----- stdout -----
+            tests/input/capture/test_capture.py::test_prints PASSED                   [ 25%]
+            tests/input/capture/test_capture.py::test_prints_and_fails FAILED         [ 50%]
+            AssertionError: 1 is not 2
+            Captured stdout:
+            some output
+            Captured stderr:
+            an <error> & a warning
+            tests/input/capture/test_capture.py::test_skipped SKIPPED (not today)     [ 75%]
+            tests/input/capture/test_capture.py::test_xfailed XFAIL (known bug)       [100%]
+            =========================== short test summary info ============================
+            FAILED tests/input/capture/test_capture.py::test_prints_and_fails - Assertion...
+            1 passed, 1 failed, 1 skipped, 1 xfailed in <TIME>s
+            generated xml file: <REPORT>
+
+            ----- stderr -----
+            "###);
+        insta::assert_snapshot!(std::fs::read_to_string(&report).unwrap(), @r###"
+        <?xml version="1.0" encoding="utf-8"?>
+        <testsuites name="pytest" errors="0" failures="1" skipped="2" tests="4" time="<TIME>">
+          <testsuite name="tests/input/capture/test_capture.py" errors="0" failures="1" skipped="2" tests="4" time="<TIME>">
+            <testcase classname="tests.input.capture.test_capture" name="test_prints" time="<TIME>">
+              <system-out>only shown in reports
+        </system-out>
+            </testcase>
+            <testcase classname="tests.input.capture.test_capture" name="test_prints_and_fails" time="<TIME>">
+              <failure message="AssertionError: 1 is not 2">Traceback (most recent call last):
+          File &quot;<FILE>&quot;, line 33, in call
+          File &quot;<FILE>&quot;, line 13, in test_prints_and_fails
+            assert 1 == 2, &quot;1 is not 2&quot;
+                   ^^^^^^
+        AssertionError: 1 is not 2
+        </failure>
+              <system-out>some output
+        </system-out>
+              <system-err>an &lt;error&gt; &amp; a warning
+        </system-err>
+            </testcase>
+            <testcase classname="tests.input.capture.test_capture" name="test_skipped" time="<TIME>">
+              <skipped type="pytest.skip" message="not today" />
+            </testcase>
+            <testcase classname="tests.input.capture.test_capture" name="test_xfailed" time="<TIME>">
+              <skipped type="pytest.xfail" message="known bug" />
+            </testcase>
+          </testsuite>
+        </testsuites>
+        "###);
+    });
+}
+
+#[test]
+fn test_report_json() {
+    let mut settings = setup();
+    settings.add_filter(
+        r#""(duration|setup|call|teardown)": ?[0-9.e-]+"#,
+        r#""$1":<TIME>"#,
+    );
+    settings.add_filter(r#""rootdir": ?"[^"]*""#, r#""rootdir":<ROOTDIR>"#);
+    settings.add_filter(
+        r#""rytest_version": ?"[^"]*""#,
+        r#""rytest_version":<VERSION>"#,
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let report = dir.path().join("report.json");
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/capture")
+            .arg("-k")
+            .arg("not fails")
+            .arg("--report-jsonl")
+            .arg("-")
+            .arg("--report-json")
+            .arg(&report), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            {"event":"session_start","schema_version":1,"rytest_version":<VERSION>,"rootdir":<ROOTDIR>}
+            {"event":"collected","nodeid":"tests/input/capture/test_capture.py::test_prints","file":"tests/input/capture/test_capture.py","markers":[],"deselected":false}
+            {"event":"collected","nodeid":"tests/input/capture/test_capture.py::test_prints_and_fails","file":"tests/input/capture/test_capture.py","markers":[],"deselected":true}
+            {"event":"collected","nodeid":"tests/input/capture/test_capture.py::test_skipped","file":"tests/input/capture/test_capture.py","markers":["skip"],"deselected":false}
+            {"event":"collected","nodeid":"tests/input/capture/test_capture.py::test_xfailed","file":"tests/input/capture/test_capture.py","markers":["xfail"],"deselected":false}
+            {"event":"test_start","nodeid":"tests/input/capture/test_capture.py::test_prints"}
+            {"event":"test_outcome","nodeid":"tests/input/capture/test_capture.py::test_prints_and_fails","outcome":"deselected","duration":<TIME>,"phases":{"setup":<TIME>,"call":<TIME>,"teardown":<TIME>},"markers":[],"stdout":"","stderr":""}
+            {"event":"test_outcome","nodeid":"tests/input/capture/test_capture.py::test_prints","outcome":"passed","duration":<TIME>,"phases":{"setup":<TIME>,"call":<TIME>,"teardown":<TIME>},"markers":[],"stdout":"only shown in reports\n","stderr":""}
+            {"event":"test_start","nodeid":"tests/input/capture/test_capture.py::test_skipped"}
+            {"event":"test_outcome","nodeid":"tests/input/capture/test_capture.py::test_skipped","outcome":"skipped","duration":<TIME>,"phases":{"setup":<TIME>,"call":<TIME>,"teardown":<TIME>},"markers":["skip"],"reason":"not today","stdout":"","stderr":""}
+            {"event":"test_start","nodeid":"tests/input/capture/test_capture.py::test_xfailed"}
+            {"event":"test_outcome","nodeid":"tests/input/capture/test_capture.py::test_xfailed","outcome":"xfailed","duration":<TIME>,"phases":{"setup":<TIME>,"call":<TIME>,"teardown":<TIME>},"markers":["xfail"],"reason":"known bug","stdout":"","stderr":""}
+            {"event":"session_finish","exitcode":0,"duration":<TIME>,"summary":{"passed":1,"failed":0,"skipped":1,"xfailed":1,"xpassed":0,"errors":0,"deselected":1},"files":[{"path":"tests/input/capture/test_capture.py","tests":3,"duration":<TIME>}]}
+
+            ----- stderr -----
+            "###);
+        insta::assert_snapshot!(std::fs::read_to_string(&report).unwrap(), @r###"
+        {
+          "schema_version": 1,
+          "rytest_version":<VERSION>,
+          "rootdir":<ROOTDIR>,
+          "exitcode": 0,
+          "duration":<TIME>,
+          "summary": {
+            "passed": 1,
+            "failed": 0,
+            "skipped": 1,
+            "xfailed": 1,
+            "xpassed": 0,
+            "errors": 0,
+            "deselected": 1
+          },
+          "files": [
+            {
+              "path": "tests/input/capture/test_capture.py",
+              "tests": 3,
+              "duration":<TIME>
+            }
+          ],
+          "tests": [
+            {
+              "nodeid": "tests/input/capture/test_capture.py::test_prints_and_fails",
+              "outcome": "deselected",
+              "duration":<TIME>,
+              "phases": {
+                "setup":<TIME>,
+                "call":<TIME>,
+                "teardown":<TIME>
+              },
+              "markers": [],
+              "stdout": "",
+              "stderr": ""
+            },
+            {
+              "nodeid": "tests/input/capture/test_capture.py::test_prints",
+              "outcome": "passed",
+              "duration":<TIME>,
+              "phases": {
+                "setup":<TIME>,
+                "call":<TIME>,
+                "teardown":<TIME>
+              },
+              "markers": [],
+              "stdout": "only shown in reports\n",
+              "stderr": ""
+            },
+            {
+              "nodeid": "tests/input/capture/test_capture.py::test_skipped",
+              "outcome": "skipped",
+              "duration":<TIME>,
+              "phases": {
+                "setup":<TIME>,
+                "call":<TIME>,
+                "teardown":<TIME>
+              },
+              "markers": [
+                "skip"
+              ],
+              "reason": "not today",
+              "stdout": "",
+              "stderr": ""
+            },
+            {
+              "nodeid": "tests/input/capture/test_capture.py::test_xfailed",
+              "outcome": "xfailed",
+              "duration":<TIME>,
+              "phases": {
+                "setup":<TIME>,
+                "call":<TIME>,
+                "teardown":<TIME>
+              },
+              "markers": [
+                "xfail"
+              ],
+              "reason": "known bug",
+              "stdout": "",
+              "stderr": ""
+            }
+          ]
+        }
+        "###);
+    });
+}
+
+#[test]
+fn test_progress() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/capture").arg("tests/input/good"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        tests/input/capture/test_capture.py .Fsx                                  [ 57%]
+        tests/input/good/test_success.py ...                                      [100%]
+        =================================== FAILURES ===================================
+        __________ tests/input/capture/test_capture.py::test_prints_and_fails __________
+        AssertionError: 1 is not 2
+        Captured stdout:
+        some output
+        Captured stderr:
+        an <error> & a warning
+        =========================== short test summary info ============================
+        FAILED tests/input/capture/test_capture.py::test_prints_and_fails - Assertion...
+        4 passed, 1 failed, 1 skipped, 1 xfailed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_quiet() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/capture").arg("tests/input/good").arg("-q"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        .Fsx...                                                                   [100%]
+        =================================== FAILURES ===================================
+        __________ tests/input/capture/test_capture.py::test_prints_and_fails __________
+        AssertionError: 1 is not 2
+        Captured stdout:
+        some output
+        Captured stderr:
+        an <error> & a warning
+        =========================== short test summary info ============================
+        FAILED tests/input/capture/test_capture.py::test_prints_and_fails - Assertion...
+        4 passed, 1 failed, 1 skipped, 1 xfailed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_quiet_quiet() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/capture").arg("tests/input/good").arg("-qq"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        =================================== FAILURES ===================================
+        __________ tests/input/capture/test_capture.py::test_prints_and_fails __________
+        AssertionError: 1 is not 2
+        Captured stdout:
+        some output
+        Captured stderr:
+        an <error> & a warning
+        =========================== short test summary info ============================
+        FAILED tests/input/capture/test_capture.py::test_prints_and_fails - Assertion...
+        4 passed, 1 failed, 1 skipped, 1 xfailed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_color() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/good").arg("--color=yes"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        tests/input/good/test_success.py [32m.[0m[32m.[0m[32m.[0m                                      [100%]
+        3 passed, 0 failed in <TIME>s
+
+        ----- stderr -----
+        "###)
+    });
+}
+
+#[test]
+fn test_report_chars() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/marks").arg("-q").arg("-ra"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        ......ss.s.xXFFss..                                                       [100%]
+        =================================== FAILURES ===================================
+        ___________ tests/input/marks/test_pytestmark.py::test_xpass_strict ____________
+        AssertionError: [XPASS(strict)]
+        _______ tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception _______
+        ValueError: not a KeyError
+        =========================== short test summary info ============================
+        SKIPPED [2] tests/input/marks/test_module_skip.py: integration environment unavailable
+        SKIPPED [1] tests/input/marks/test_pytestmark.py: never on this platform
+        SKIPPED [2] tests/input/marks/test_pytestmark.py: whole class
+        XFAIL tests/input/marks/test_pytestmark.py::test_xfail - known bug
+        XPASS tests/input/marks/test_pytestmark.py::test_xpass
+        FAILED tests/input/marks/test_pytestmark.py::test_xpass_strict - AssertionErr...
+        FAILED tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception - Val...
+        10 passed, 2 failed, 5 skipped, 1 xfailed, 1 xpassed in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_report_chars_passes() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/capture").arg("-qq").arg("-rfNpP"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        =================================== FAILURES ===================================
+        __________ tests/input/capture/test_capture.py::test_prints_and_fails __________
+        AssertionError: 1 is not 2
+        Captured stdout:
+        some output
+        Captured stderr:
+        an <error> & a warning
+        ==================================== PASSES ====================================
+        _______________ tests/input/capture/test_capture.py::test_prints _______________
+        Captured stdout:
+        only shown in reports
+        =========================== short test summary info ============================
+        PASSED tests/input/capture/test_capture.py::test_prints
+        1 passed, 1 failed, 1 skipped, 1 xfailed in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_report_chars_warnings() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/capture").arg("-qq").arg("-rfEw"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        =================================== FAILURES ===================================
+        __________ tests/input/capture/test_capture.py::test_prints_and_fails __________
+        AssertionError: 1 is not 2
+        Captured stdout:
+        some output
+        Captured stderr:
+        an <error> & a warning
+        =========================== short test summary info ============================
+        FAILED tests/input/capture/test_capture.py::test_prints_and_fails - Assertion...
+        1 passed, 1 failed, 1 skipped, 1 xfailed in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_missing_path() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/good").arg("does/not/exist"), @r###"
+        success: false
+        exit_code: 4
+        ----- stdout -----
+
+        ----- stderr -----
+        file or directory not found: does/not/exist
+        "###);
+    });
+}
+
+#[test]
+fn test_missing_node_id_file() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("does/not/exist.py::test_function"), @r###"
+        success: false
+        exit_code: 4
+        ----- stdout -----
+
+        ----- stderr -----
+        file or directory not found: does/not/exist.py::test_function
+        "###);
+    });
+}
+
+#[test]
+fn test_report_chars_invalid() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/good").arg("-rfz"), @r###"
+        success: false
+        exit_code: 4
+        ----- stdout -----
+
+        ----- stderr -----
+        Unknown character 'z' passed to '-r', expected some of fEsxXpPwaAN
+        "###);
+    });
+}
+
+#[test]
+fn test_durations() {
+    let mut settings = setup();
+    settings.add_filter(r"\d+\.\d{2}s (setup|call|teardown)", "<TIME>s $1");
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/durations")
+            .arg("-q")
+            .arg("--durations=0")
+            .arg("--durations-min=0.5"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            ..                                                                        [100%]
+            ============================== slowest durations ===============================
+            <TIME>s call     tests/input/durations/test_durations.py::test_slow
+            <TIME>s setup    tests/input/durations/test_durations.py::test_slow
+            <TIME>s teardown tests/input/durations/test_durations.py::test_slow
+
+            (3 durations < 0.5s hidden.  Use -vv to show these durations.)
+            2 passed, 0 failed in <TIME>s
+
+            ----- stderr -----
+            "###);
+    });
+}
+
+#[test]
+fn test_durations_hidden() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/good")
+            .arg("-q")
+            .arg("--durations=2")
+            .arg("--durations-min=10"), @r###"
+            success: true
+            exit_code: 0
+            ----- stdout -----
+            ...                                                                       [100%]
+            ============================= slowest 2 durations ==============================
+
+            (2 durations < 10s hidden.  Use -vv to show these durations.)
+            3 passed, 0 failed in <TIME>s
+
+            ----- stderr -----
+            "###);
+    });
+}
+
+#[test]
+fn test_collection_errors() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/bad").arg("tests/input/good"), @r###"
+        success: false
+        exit_code: 2
+        ----- stdout -----
+        ==================================== ERRORS ====================================
+        _____________ ERROR collecting tests/input/bad/test_other_error.py _____________
+        SyntaxError:  Error parsing expected an indented block at byte offset 30
+        =========================== short test summary info ============================
+        ERROR tests/input/bad/test_other_error.py - SyntaxError:  Error parsing expec...
+        !!!!!!!!!!!!!!!!!!!! Interrupted: 1 error during collection !!!!!!!!!!!!!!!!!!!!
+        0 passed, 0 failed, 1 error in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_continue_on_collection_errors() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/bad")
+            .arg("tests/input/good")
+            .arg("--continue-on-collection-errors"), @r###"
+            success: false
+            exit_code: 1
+            ----- stdout -----
+            tests/input/bad/test_other_file.py .F                                     [ 40%]
+            tests/input/good/test_success.py ...                                      [100%]
+            ==================================== ERRORS ====================================
+            _____________ ERROR collecting tests/input/bad/test_other_error.py _____________
+            SyntaxError:  Error parsing expected an indented block at byte offset 30
+            =================================== FAILURES ===================================
+            ___________ tests/input/bad/test_other_file.py::test_function_fails ____________
+            AssertionError: 
+            =========================== short test summary info ============================
+            FAILED tests/input/bad/test_other_file.py::test_function_fails - AssertionError:
+            ERROR tests/input/bad/test_other_error.py - SyntaxError:  Error parsing expec...
+            4 passed, 1 failed, 1 error in <TIME>s
+
+            ----- stderr -----
+            "###);
+    });
+}
+
+#[test]
+fn test_no_tests_collected() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/good").arg("-k").arg("nothing_matches"), @r###"
+        success: false
+        exit_code: 5
+        ----- stdout -----
+        0 passed, 0 failed, 3 deselected in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_keyboard_interrupt() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/interrupt").arg("-q"), @r###"
+        success: false
+        exit_code: 2
+        ----- stdout -----
+        .F                                                                        [ 66%]
+        =================================== FAILURES ===================================
+        ___________ tests/input/interrupt/test_interrupt.py::test_interrupts ___________
+        KeyboardInterrupt: 
+        =========================== short test summary info ============================
+        FAILED tests/input/interrupt/test_interrupt.py::test_interrupts - KeyboardInt...
+        !!!!!!!!!!!!!!!!!!!!!!!!!!!!!! KeyboardInterrupt !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
+        1 passed, 1 failed in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_exitfirst() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/capture").arg("tests/input/good").arg("-x"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        tests/input/capture/test_capture.py .F                                    [ 28%]
+        =================================== FAILURES ===================================
+        __________ tests/input/capture/test_capture.py::test_prints_and_fails __________
+        AssertionError: 1 is not 2
+        Captured stdout:
+        some output
+        Captured stderr:
+        an <error> & a warning
+        =========================== short test summary info ============================
+        FAILED tests/input/capture/test_capture.py::test_prints_and_fails - Assertion...
+        !!!!!!!!!!!!!!!!!!!!!!!!!! stopping after 1 failures !!!!!!!!!!!!!!!!!!!!!!!!!!!
+        1 passed, 1 failed in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_maxfail() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli().arg("tests/input/marks").arg("tests/input/good").arg("-q").arg("--maxfail=2"), @r###"
+        success: false
+        exit_code: 1
+        ----- stdout -----
+        ......ss.s.xXFF                                                           [ 68%]
+        =================================== FAILURES ===================================
+        ___________ tests/input/marks/test_pytestmark.py::test_xpass_strict ____________
+        AssertionError: [XPASS(strict)]
+        _______ tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception _______
+        ValueError: not a KeyError
+        =========================== short test summary info ============================
+        FAILED tests/input/marks/test_pytestmark.py::test_xpass_strict - AssertionErr...
+        FAILED tests/input/marks/test_pytestmark.py::test_xfail_wrong_exception - Val...
+        !!!!!!!!!!!!!!!!!!!!!!!!!! stopping after 2 failures !!!!!!!!!!!!!!!!!!!!!!!!!!!
+        8 passed, 2 failed, 3 skipped, 1 xfailed, 1 xpassed in <TIME>s
+
+        ----- stderr -----
+        "###);
+    });
+}
+
+#[test]
+fn test_exitfirst_collection_error() {
+    let settings = setup();
+
+    settings.bind(|| {
+        assert_cmd_snapshot!(cli()
+            .arg("tests/input/bad")
+            .arg("tests/input/good")
+            .arg("--continue-on-collection-errors")
+            .arg("-x"), @r###"
+            success: false
+            exit_code: 1
+            ----- stdout -----
+            ==================================== ERRORS ====================================
+            _____________ ERROR collecting tests/input/bad/test_other_error.py _____________
+            SyntaxError:  Error parsing expected an indented block at byte offset 30
+            =========================== short test summary info ============================
+            ERROR tests/input/bad/test_other_error.py - SyntaxError:  Error parsing expec...
+            !!!!!!!!!!!!!!!!!!!!!!!!!! stopping after 1 failures !!!!!!!!!!!!!!!!!!!!!!!!!!!
+            0 passed, 0 failed, 1 error in <TIME>s
+
+            ----- stderr -----
+            "###);
+    });
+}
@@ -0,0 +1,10 @@
+import pytest
+
+
+def check_fast():
+    pass
+
+
+@pytest.mark.slow
+def check_slow():
+    pass
@@ -0,0 +1,96 @@
+import asyncio
+
+import pytest
+
+events = []
+
+
+@pytest.fixture
+async def async_value():
+    await asyncio.sleep(0)
+    return 42
+
+
+@pytest.fixture
+async def async_resource():
+    events.append("setup")
+    yield asyncio.get_running_loop()
+    events.append("teardown")
+
+
+async def helper():
+    await asyncio.sleep(0)
+
+
+async def test_async_passes():
+    await asyncio.sleep(0)
+
+
+async def test_async_fails():
+    await asyncio.sleep(0)
+    assert False
+
+
+async def test_async_fixture(async_value):
+    assert async_value == 42
+
+
+async def test_async_generator_fixture(async_resource):
+    assert async_resource is asyncio.get_running_loop()
+    assert events[-1] == "setup"
+
+
+def test_async_generator_fixture_torn_down():
+    assert events[-1] == "teardown"
+
+
+def test_unawaited_coroutine():
+    helper()
+
+
+class TestAsyncMethods:
+    async def test_method(self, async_value):
+        assert async_value == 42
+
+
+scoped = []
+
+
+@pytest.fixture(scope="module")
+async def module_loop():
+    scoped.append("module")
+    yield asyncio.get_running_loop()
+    scoped.append("module teardown")
+
+
+@pytest.fixture(scope="class")
+async def class_loop():
+    scoped.append("class")
+    yield asyncio.get_running_loop()
+    scoped.append("class teardown")
+
+
+@pytest.fixture()
+def per_test():
+    scoped.append("function")
+    return len(scoped)
+
+
+async def test_module_fixture(module_loop, per_test):
+    # the module's loop outlives the test's own
+    assert module_loop is not asyncio.get_running_loop()
+    assert not module_loop.is_closed()
+
+
+class TestScopedFixtures:
+    async def test_first(self, module_loop, class_loop):
+        assert class_loop is not module_loop
+        assert class_loop is not asyncio.get_running_loop()
+
+    async def test_second(self, module_loop, class_loop, per_test):
+        assert scoped == ["module", "function", "class", "function"]
+
+
+def test_class_fixture_torn_down():
+    assert scoped[-1] == "class teardown"
+    assert "module teardown" not in scoped
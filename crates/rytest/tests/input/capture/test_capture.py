@@ -0,0 +1,23 @@
+import sys
+
+import pytest
+
+
+def test_prints():
+    print("only shown in reports")
+
+
+def test_prints_and_fails():
+    print("some output")
+    print("an <error> & a warning", file=sys.stderr)
+    assert 1 == 2, "1 is not 2"
+
+
+@pytest.mark.skip(reason="not today")
+def test_skipped():
+    pass
+
+
+@pytest.mark.xfail(reason="known bug")
+def test_xfailed():
+    assert False
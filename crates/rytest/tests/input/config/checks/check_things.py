@@ -0,0 +1,20 @@
+import pytest
+from helper import answer
+
+
+def check_pythonpath():
+    assert answer() == 42
+
+
+@pytest.mark.slow
+def check_marker():
+    pass
+
+
+def test_not_collected():
+    pass
+
+
+class CheckClass:
+    def check_method(self):
+        pass
@@ -0,0 +1,6 @@
+def verify_glob_patterns():
+    pass
+
+
+def check_glob_patterns():
+    pass
@@ -0,0 +1,2 @@
+def check_in_norecursedir():
+    pass
@@ -0,0 +1,42 @@
+"""
+Simple arithmetic.
+
+>>> add(2, 2)
+4
+"""
+
+
+def add(a, b):
+    """
+    >>> add(1, 2)
+    3
+    """
+    return a + b
+
+
+def numbers(n):
+    """
+    >>> numbers(20)
+    [0, 1, ..., 19]
+    """
+    return list(range(n))
+
+
+def undocumented():
+    return None
+
+
+class Calculator:
+    """
+    >>> Calculator().total
+    0
+    """
+
+    total = 0
+
+    def subtract(self, a, b):
+        """
+        >>> Calculator().subtract(3, 1)
+        2
+        """
+        return a - b
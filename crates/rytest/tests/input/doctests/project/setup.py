@@ -0,0 +1,8 @@
+"""Packaging script, which pytest doesn't import for doctests.
+
+>>> 1 + 1
+3
+"""
+from setuptools import setup
+
+setup(name="project")
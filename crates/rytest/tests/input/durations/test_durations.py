@@ -0,0 +1,15 @@
+import time
+
+
+def slow_fixture():
+    time.sleep(1.0)
+    yield
+    time.sleep(0.6)
+
+
+def test_slow(slow_fixture):
+    time.sleep(1.5)
+
+
+def test_quick():
+    pass
@@ -0,0 +1,42 @@
+import sys
+
+if sys.version_info >= (3, 0):
+    def test_on_python3():
+        pass
+else:
+    def test_on_python3():
+        assert False
+
+try:
+    from pytest import mark
+except ImportError:
+    mark = None
+
+
+@mark.slow
+def test_after_try_import():
+    pass
+
+
+def _make_test(value):
+    def check():
+        assert value >= 0
+    return check
+
+
+for _value in range(2):
+    globals()["test_generated_%d" % _value] = _make_test(_value)
+
+
+def _make_method(value):
+    def check(self):
+        assert value >= 0
+    return check
+
+
+class TestGeneratedMethods:
+    pass
+
+
+for _value in range(2):
+    setattr(TestGeneratedMethods, "test_method_%d" % _value, _make_method(_value))
@@ -0,0 +1,29 @@
+import pytest
+from pytest import mark
+
+pytestmark = pytest.mark.integration
+
+
+@pytest.mark.slow
+def test_slow():
+    pass
+
+
+@mark.slow
+@mark.db
+def test_slow_db():
+    pass
+
+
+def test_unmarked():
+    pass
+
+
+@pytest.mark.db
+class TestMarkedClass:
+    def test_in_class(self):
+        pass
+
+    @pytest.mark.slow
+    def test_slow_in_class(self):
+        pass
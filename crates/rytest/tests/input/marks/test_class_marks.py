@@ -0,0 +1,31 @@
+import pytest
+
+calls = []
+
+
+@pytest.fixture
+def record_call():
+    calls.append("record_call")
+    yield
+    calls.append("record_call teardown")
+
+
+@pytest.mark.usefixtures("record_call")
+def test_function_usefixtures():
+    assert calls[-1] == "record_call"
+
+
+@pytest.mark.usefixtures("record_call")
+class TestUsefixtures:
+    def test_method(self):
+        assert calls[-1] == "record_call"
+
+
+@pytest.mark.parametrize("a, b", [(1, 2), (3, 4)])
+class TestParametrizedClass:
+    def test_less(self, a, b):
+        assert a < b
+
+    @pytest.mark.parametrize("c", [5])
+    def test_nested(self, a, b, c):
+        assert a < b < c
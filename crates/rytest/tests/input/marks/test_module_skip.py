@@ -0,0 +1,11 @@
+import pytest
+
+pytestmark = pytest.mark.skip(reason="integration environment unavailable")
+
+
+def test_first():
+    assert False
+
+
+def test_second():
+    assert False
@@ -0,0 +1,63 @@
+import sys
+
+import pytest
+
+pytestmark = [pytest.mark.usefixtures("module_fixture"), pytest.mark.slow]
+
+calls = []
+
+
+@pytest.fixture
+def module_fixture():
+    calls.append("module_fixture")
+
+
+def test_uses_module_fixture():
+    assert calls[-1] == "module_fixture"
+
+
+@pytest.mark.skipif("sys.platform != 'nonexistent'", reason="never on this platform")
+def test_skipif_string():
+    assert False
+
+
+@pytest.mark.skipif(sys.version_info < (3,), reason="python 2")
+def test_skipif_false():
+    pass
+
+
+@pytest.mark.xfail(reason="known bug")
+def test_xfail():
+    assert False
+
+
+@pytest.mark.xfail
+def test_xpass():
+    pass
+
+
+@pytest.mark.xfail(strict=True)
+def test_xpass_strict():
+    pass
+
+
+@pytest.mark.xfail(raises=KeyError)
+def test_xfail_wrong_exception():
+    raise ValueError("not a KeyError")
+
+
+class TestSkipped:
+    pytestmark = pytest.mark.skip(reason="whole class")
+
+    def test_one(self):
+        assert False
+
+    def test_two(self):
+        assert False
+
+
+class TestParametrized:
+    pytestmark = pytest.mark.parametrize("value", [1, 2])
+
+    def test_value(self, value):
+        pass
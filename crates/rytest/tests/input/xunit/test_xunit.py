@@ -0,0 +1,86 @@
+import unittest
+
+import pytest
+
+calls = []
+
+
+def setup_module(module):
+    calls.append("setup_module")
+
+
+def teardown_module():
+    calls.append("teardown_module")
+
+
+def setup_function(function):
+    calls.append("setup_function:" + function.__name__)
+
+
+def teardown_function(function):
+    calls.append("teardown_function:" + function.__name__)
+
+
+@pytest.fixture
+def resource():
+    calls.append("fixture")
+    yield 42
+    calls.append("fixture_teardown")
+
+
+def test_module_setup(resource):
+    assert calls == ["setup_module", "setup_function:test_module_setup", "fixture"]
+
+
+def test_function_teardown():
+    assert calls[-3:] == [
+        "fixture_teardown",
+        "teardown_function:test_module_setup",
+        "setup_function:test_function_teardown",
+    ]
+
+
+class TestXunitClass:
+    @classmethod
+    def setup_class(cls):
+        calls.append("setup_class")
+
+    @classmethod
+    def teardown_class(cls):
+        calls.append("teardown_class")
+
+    def setup_method(self, method):
+        calls.append("setup_method:" + method.__name__)
+
+    def teardown_method(self, method):
+        calls.append("teardown_method:" + method.__name__)
+
+    def test_class_setup(self):
+        assert calls[-3:] == [
+            "teardown_function:test_function_teardown",
+            "setup_class",
+            "setup_method:test_class_setup",
+        ]
+
+    def test_method_teardown(self):
+        assert calls[-2:] == [
+            "teardown_method:test_class_setup",
+            "setup_method:test_method_teardown",
+        ]
+
+
+class UnittestClass(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        calls.append("setUpClass")
+
+    def setUp(self):
+        calls.append("setUp")
+
+    def test_unittest_setup(self):
+        assert calls[-4:] == [
+            "teardown_method:test_method_teardown",
+            "teardown_class",
+            "setUpClass",
+            "setUp",
+        ]